@@ -66,6 +66,25 @@ impl Adapter {
 		}
 	}
 
+	/// A conservative ceiling on the size of an encoded image, so the previewer
+	/// can downscale before emitting. `None` means no practical limit.
+	#[inline]
+	pub fn image_max_bytes(self) -> Option<usize> { self.max_bytes(*TMUX) }
+
+	// tmux drops passthrough sequences larger than its 1 MiB input buffer, which
+	// only matters for protocols that emit the image in a single sequence,
+	// since KGP is sent in 4 KiB chunks each wrapped separately.
+	fn max_bytes(self, tmux: bool) -> Option<usize> {
+		match self {
+			Self::Kgp | Self::KgpOld => None,
+			Self::Iip if tmux => Some(1 << 20),
+			Self::Iip => None,
+			Self::Sixel if tmux => Some(1 << 20),
+			Self::Sixel => Some(8 << 20),
+			Self::X11 | Self::Wayland | Self::Chafa => None,
+		}
+	}
+
 	#[inline]
 	pub fn shown_load(self) -> Option<Rect> { SHOWN.load_full().map(|r| *r) }
 
@@ -81,15 +100,14 @@ impl Adapter {
 }
 
 impl Adapter {
-	pub fn matches() -> Self {
-		let emulator = Emulator::detect();
+	pub fn matches(emulator: Emulator) -> Self {
 		if matches!(emulator, Emulator::Microsoft) {
-			return Self::Sixel;
+			return emulator.detect_adapters().first().copied().unwrap_or(Self::Chafa);
 		} else if *WSL && matches!(emulator, Emulator::WezTerm) {
 			return Self::KgpOld;
 		}
 
-		let mut protocols = emulator.adapters();
+		let mut protocols = emulator.detect_adapters();
 		#[cfg(windows)]
 		protocols.retain(|p| *p == Self::Iip);
 		if env_exists("ZELLIJ_SESSION_NAME") {
@@ -119,3 +137,17 @@ impl Adapter {
 		Self::Chafa
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_max_bytes() {
+		assert!(Adapter::Sixel.max_bytes(true) < Adapter::Sixel.max_bytes(false));
+		assert_eq!(Adapter::Iip.max_bytes(true), Some(1 << 20));
+		assert_eq!(Adapter::Iip.max_bytes(false), None);
+		assert_eq!(Adapter::Kgp.max_bytes(true), None);
+		assert_eq!(Adapter::Chafa.max_bytes(false), None);
+	}
+}
@@ -6,7 +6,7 @@ use crossterm::{cursor::MoveTo, queue};
 use ratatui::layout::Rect;
 use tokio::process::Command;
 
-use crate::{Adapter, Emulator};
+use crate::{Adapter, EMULATOR, Emulator};
 
 pub(super) struct Chafa;
 
@@ -27,6 +27,7 @@ impl Chafa {
 				"--view-size",
 			])
 			.arg(format!("{}x{}", max.width, max.height))
+			.args(if EMULATOR.truecolor() { &[][..] } else { &["--colors", "256"] })
 			.arg(path)
 			.stdin(Stdio::null())
 			.stdout(Stdio::piped())
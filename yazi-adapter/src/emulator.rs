@@ -1,15 +1,21 @@
-use std::{io::{LineWriter, stderr}, time::Duration};
+use std::{io::{IsTerminal, LineWriter, Write, stderr, stdout}, sync::OnceLock, time::Duration};
 
 use anyhow::{Result, bail};
 use crossterm::{cursor::{RestorePosition, SavePosition}, execute, style::Print, terminal::{disable_raw_mode, enable_raw_mode}};
 use scopeguard::defer;
-use tokio::{io::{AsyncReadExt, BufReader}, time::timeout};
-use tracing::{error, warn};
-use yazi_shared::env_exists;
+use tokio::{io::{AsyncRead, AsyncReadExt, BufReader}, select, time::{Instant, timeout_at}};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, warn};
 
-use crate::{Adapter, Mux, TMUX};
+use crate::{Adapter, CsiProbe, EMULATOR, Mux, ProbeTiming, SixelOpts, TMUX, detect_via_hooks};
 
-#[derive(Clone, Debug)]
+// Whether synchronized output is supported, the number of Sixel color
+// registers, and the XTVERSION payload, as answered to the CSI probe
+static SYNC_OUTPUT: OnceLock<bool> = OnceLock::new();
+static SIXEL_REGISTERS: OnceLock<Option<u16>> = OnceLock::new();
+static VERSION: OnceLock<Option<String>> = OnceLock::new();
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Emulator {
 	Unknown(Vec<Adapter>),
 	Kitty,
@@ -28,9 +34,70 @@ pub enum Emulator {
 	Neovim,
 	Apple,
 	Urxvt,
+	Contour,
+	Wayst,
+	Bobcat,
+	Eat,
 }
 
 impl Emulator {
+	/// All known emulators, excluding [`Emulator::Unknown`].
+	pub fn all() -> &'static [Self] {
+		&[
+			Self::Kitty,
+			Self::Konsole,
+			Self::Iterm2,
+			Self::WezTerm,
+			Self::Foot,
+			Self::Ghostty,
+			Self::Microsoft,
+			Self::Rio,
+			Self::BlackBox,
+			Self::VSCode,
+			Self::Tabby,
+			Self::Hyper,
+			Self::Mintty,
+			Self::Neovim,
+			Self::Apple,
+			Self::Urxvt,
+			Self::Contour,
+			Self::Wayst,
+			Self::Bobcat,
+			Self::Eat,
+		]
+	}
+
+	/// The `(name, default adapters)` pairs of all known emulators.
+	pub fn table() -> Vec<(&'static str, Vec<Adapter>)> {
+		Self::all().iter().map(|e| (e.name(), e.clone().adapters())).collect()
+	}
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			Self::Unknown(_) => "Unknown",
+			Self::Kitty => "Kitty",
+			Self::Konsole => "Konsole",
+			Self::Iterm2 => "iTerm2",
+			Self::WezTerm => "WezTerm",
+			Self::Foot => "foot",
+			Self::Ghostty => "Ghostty",
+			Self::Microsoft => "Windows Terminal",
+			Self::Rio => "Rio",
+			Self::BlackBox => "Black Box",
+			Self::VSCode => "VSCode",
+			Self::Tabby => "Tabby",
+			Self::Hyper => "Hyper",
+			Self::Mintty => "Mintty",
+			Self::Neovim => "Neovim",
+			Self::Apple => "Apple Terminal",
+			Self::Urxvt => "urxvt",
+			Self::Contour => "Contour",
+			Self::Wayst => "Wayst",
+			Self::Bobcat => "Bobcat",
+			Self::Eat => "Eat",
+		}
+	}
+
 	pub fn adapters(self) -> Vec<Adapter> {
 		match self {
 			Self::Unknown(adapters) => adapters,
@@ -50,14 +117,282 @@ impl Emulator {
 			Self::Neovim => vec![],
 			Self::Apple => vec![],
 			Self::Urxvt => vec![],
+			Self::Contour => vec![Adapter::Sixel],
+			Self::Wayst => vec![Adapter::Sixel],
+			Self::Bobcat => vec![Adapter::Kgp, Adapter::Sixel],
+			Self::Eat => vec![],
+		}
+	}
+
+	/// The sequence that clears all images drawn by this emulator's preferred
+	/// adapter, `None` if it draws no images or they can't be cleared this way.
+	pub fn clear_images_seq(&self) -> Option<String> {
+		Self::clear_seq(*self.clone().adapters().first()?, *TMUX)
+	}
+
+	/// Clears any images left over on screen, e.g. by a previous crash, with the
+	/// sequences of all adapters this emulator supports. Only done once.
+	pub fn reset_all_images(&self) -> std::io::Result<()> {
+		static ONCE: std::sync::Once = std::sync::Once::new();
+
+		let mut result = Ok(());
+		ONCE.call_once(|| result = self.write_reset(&mut stderr(), *TMUX));
+		result
+	}
+
+	fn write_reset(&self, w: &mut impl Write, tmux: bool) -> std::io::Result<()> {
+		let mut seqs: Vec<_> =
+			self.clone().adapters().into_iter().filter_map(|a| Self::clear_seq(a, tmux)).collect();
+		seqs.dedup();
+
+		if !seqs.is_empty() {
+			w.write_all(seqs.concat().as_bytes())?;
+			w.flush()?;
+		}
+		Ok(())
+	}
+
+	fn clear_seq(adapter: Adapter, tmux: bool) -> Option<String> {
+		match adapter {
+			Adapter::Kgp | Adapter::KgpOld => {
+				Some(Mux::csi_with("\x1b_Gq=2,a=d,d=A\x1b\\", tmux).into_owned())
+			}
+			// Sixel and IIP images live in the cells, so erasing the display clears them,
+			// which tmux handles itself, hence no passthrough
+			Adapter::Iip | Adapter::Sixel => Some("\x1b[2J".to_owned()),
+			Adapter::X11 | Adapter::Wayland | Adapter::Chafa => None,
+		}
+	}
+
+	/// Default Sixel encoding options for this emulator. Unknown emulators are
+	/// taken as xterm-like with 16 color registers, so dither to make up for it.
+	pub fn sixel_opts(&self) -> SixelOpts {
+		match self {
+			Self::Unknown(_) => SixelOpts { palette: 16, dither: true },
+			_ => SixelOpts::default(),
+		}
+	}
+
+	/// The number of colors Sixel images may use, as many as the color registers
+	/// the terminal reports, up to 256, which is also assumed if it doesn't
+	/// report any.
+	pub fn sixel_palette_size(&self) -> u16 { Self::palette_size(Self::sixel_registers()) }
+
+	fn palette_size(registers: Option<u16>) -> u16 {
+		registers.filter(|&n| n > 0).map_or(256, |n| n.min(256))
+	}
+
+	/// The number of Sixel color registers, as reported to XTSMGRAPHICS (`CSI ? 1
+	/// ; 1 ; 0 S`). Taken from the CSI probe, only queried on its own if that
+	/// didn't run.
+	pub fn sixel_registers() -> Option<u16> {
+		*SIXEL_REGISTERS.get_or_init(|| Self::parse_sixel_registers(&Self::query("\x1b[?1;1;0S")?))
+	}
+
+	// A `CSI ? 1 ; Ps ; Pv S` reply, where a `Ps` of 0 means success
+	fn parse_sixel_registers(resp: &str) -> Option<u16> {
+		let (_, s) = resp.split_once("\x1b[?1;0;")?;
+		s.split_once('S')?.0.parse().ok()
+	}
+
+	/// Whether the emulator honors IIP's `preserveAspectRatio`. VSCode's image
+	/// addon ignores it and stretches the image, so the exact size is used.
+	pub fn iip_preserve_aspect(&self) -> bool { !matches!(self, Self::VSCode) }
+
+	/// Whether the emulator draws images itself, with any of the protocols.
+	pub fn supports_images(&self) -> bool { !self.clone().adapters().is_empty() }
+
+	/// Whether 24-bit colors are supported. Apple Terminal only has 256 colors,
+	/// and unknown emulators are taken at their word in `COLORTERM`.
+	pub fn truecolor(&self) -> bool {
+		self.truecolor_with(std::env::var("COLORTERM").ok().as_deref())
+	}
+
+	fn truecolor_with(&self, colorterm: Option<&str>) -> bool {
+		match self {
+			Self::Apple => false,
+			Self::Unknown(_) => matches!(colorterm, Some("truecolor" | "24bit")),
+			_ => true,
+		}
+	}
+
+	/// Whether OSC 8 hyperlinks are supported, by the known emulators that do,
+	/// or VTE since 0.50 as told by `VTE_VERSION`. False if unsure.
+	pub fn supports_hyperlinks(&self) -> bool {
+		self.hyperlinks_with(std::env::var("VTE_VERSION").ok().as_deref())
+	}
+
+	fn hyperlinks_with(&self, vte_version: Option<&str>) -> bool {
+		match self {
+			Self::Kitty
+			| Self::Konsole
+			| Self::Iterm2
+			| Self::WezTerm
+			| Self::Foot
+			| Self::Ghostty
+			| Self::Microsoft
+			| Self::Rio
+			| Self::BlackBox
+			| Self::VSCode
+			| Self::Mintty
+			| Self::Contour => true,
+			// `VTE_VERSION` is like `7600` for 0.76.0
+			Self::Unknown(_) => {
+				vte_version.and_then(|v| v.parse::<u32>().ok()).is_some_and(|v| v >= 5000)
+			}
+			_ => false,
+		}
+	}
+
+	/// Whether the cursor has to be shown explicitly after drawing an image, as
+	/// Konsole hides it when placing one and leaves it hidden.
+	pub fn needs_cursor_show_after_image(&self) -> bool { matches!(self, Self::Konsole) }
+
+	/// Adapters supported by the running emulator, refining [`Self::adapters`]
+	/// with version information for the emulators where it matters.
+	pub fn detect_adapters(self) -> Vec<Adapter> {
+		match self {
+			Self::Iterm2 => Self::iterm2_adapters(Self::version_string().as_deref()),
+			Self::Ghostty => {
+				Self::ghostty_adapters(std::env::var("TERM_PROGRAM_VERSION").ok().as_deref())
+			}
+			Self::VSCode => Self::vscode_adapters(std::env::var("TERM_PROGRAM_VERSION").ok().as_deref()),
+			Self::Microsoft => Self::microsoft_adapters(Self::version_string().as_deref()),
+			e => e.adapters(),
+		}
+	}
+
+	#[inline]
+	fn ghostty_adapters(version: Option<&str>) -> Vec<Adapter> {
+		Self::ghostty_adapters_on(version, cfg!(target_os = "macos"))
+	}
+
+	// Ghostty only gained Sixel in 1.2 on Linux, while the macOS build drew it
+	// wrong until 1.3, so older or unknown versions stay on KGP
+	fn ghostty_adapters_on(version: Option<&str>, macos: bool) -> Vec<Adapter> {
+		let sixel = if macos { (1, 3, 0) } else { (1, 2, 0) };
+		match version.and_then(parse_version) {
+			Some(v) if v >= sixel => vec![Adapter::Kgp, Adapter::Sixel],
+			_ => Self::Ghostty.adapters(),
+		}
+	}
+
+	// Sixel in the VSCode integrated terminal renders garbage before 1.86,
+	// while IIP works as long as images are enabled at all
+	fn vscode_adapters(version: Option<&str>) -> Vec<Adapter> {
+		match version.and_then(parse_version) {
+			Some(v) if v < (1, 86, 0) => vec![Adapter::Iip],
+			_ => Self::VSCode.adapters(),
+		}
+	}
+
+	/// Whether KGP Unicode placeholders are supported, so images can be placed
+	/// as text cells instead of directly.
+	pub fn kgp_unicode_placeholders(&self) -> bool {
+		matches!(self, Self::Kitty | Self::Ghostty)
+			&& self.unicode_placeholders(Self::version_string().as_deref())
+	}
+
+	// Kitty added them in 0.28, and Ghostty has had them since its first release
+	fn unicode_placeholders(&self, xtversion: Option<&str>) -> bool {
+		let version =
+			xtversion.and_then(|s| s.split_once(['(', ' '])).and_then(|(_, v)| parse_version(v));
+		match (self, version) {
+			(Self::Kitty, Some(v)) => v >= (0, 28, 0),
+			(Self::Ghostty, Some(v)) => v >= (1, 0, 0),
+			_ => false,
+		}
+	}
+
+	// Windows Terminal only gained Sixel in 1.22, and renders garbage before that.
+	// It sets no version variable, so it's taken from the XTVERSION reply, and
+	// builds that don't report one are given the benefit of the doubt.
+	fn microsoft_adapters(xtversion: Option<&str>) -> Vec<Adapter> {
+		match xtversion.and_then(|s| s.rsplit(' ').next()).and_then(parse_version) {
+			Some(v) if v < (1, 22, 0) => vec![],
+			_ => Self::Microsoft.adapters(),
+		}
+	}
+
+	// iTerm2 supports KGP since 3.6, which handles scrolling better than IIP
+	fn iterm2_adapters(xtversion: Option<&str>) -> Vec<Adapter> {
+		match xtversion.and_then(|s| s.strip_prefix("iTerm2 ")).and_then(parse_version) {
+			Some(v) if v >= (3, 6, 0) => vec![Adapter::Kgp, Adapter::Iip, Adapter::Sixel],
+			_ => Self::Iterm2.adapters(),
 		}
 	}
 }
 
 impl Emulator {
+	// Emulators told by the name in their XTVERSION reply,
+	// a new terminal that identifies itself this way only needs an entry here
+	const XTVERSIONS: [(&'static str, Self); 9] = [
+		("kitty", Self::Kitty),
+		("Konsole", Self::Konsole),
+		("iTerm2", Self::Iterm2),
+		("WezTerm", Self::WezTerm),
+		("foot", Self::Foot),
+		("ghostty", Self::Ghostty),
+		("contour", Self::Contour),
+		("wayst", Self::Wayst),
+		("bobcat", Self::Bobcat),
+	];
+
 	pub fn detect() -> Self {
-		if env_exists("NVIM_LOG_FILE") && env_exists("NVIM") {
-			return Self::Neovim;
+		if let Some(emulator) = detect_via_hooks() {
+			debug!("[Adapter] Emulator decided by a detect hook: {emulator:?}");
+			return emulator;
+		}
+
+		if *TMUX && Mux::passthrough_enabled() == Some(false) {
+			error!(
+				"[Adapter] tmux `allow-passthrough` is off, images will not be shown. Run `tmux set -g allow-passthrough on` to enable it"
+			);
+		}
+
+		match Self::via_vars(|k| std::env::var(k).ok(), Self::via_env) {
+			// `TERM=contour` is often carried over SSH to other terminals, so confirm it
+			Some(Self::Contour) => Self::merge(Self::Contour, Self::via_csi().ok()),
+			Some(e) => e,
+			None => Self::via_csi().unwrap_or(Self::Unknown(vec![])),
+		}
+	}
+
+	// Combines the emulator told by the environment with the one from the CSI
+	// probe, preferring the latter as it reflects the actual terminal, unless
+	// the probe couldn't tell
+	fn merge(env: Self, csi: Option<Self>) -> Self {
+		match csi {
+			Some(Self::Unknown(adapters))
+				if !adapters.is_empty() && adapters != env.clone().adapters() =>
+			{
+				warn!("[Adapter] {env:?} from the environment disagrees with the CSI probe: {adapters:?}");
+				Self::Unknown(adapters)
+			}
+			Some(Self::Unknown(_)) | None => env,
+			Some(csi) => {
+				if csi != env {
+					warn!("[Adapter] {env:?} from the environment disagrees with the CSI probe: {csi:?}");
+				}
+				csi
+			}
+		}
+	}
+
+	// Detects the emulator from environment variables only,
+	// returns `None` if the CSI probe is needed to tell.
+	fn via_vars(
+		var: impl Fn(&str) -> Option<String>,
+		via_env: impl FnOnce() -> (String, String),
+	) -> Option<Self> {
+		let exists = |name: &str| var(name).is_some_and(|s| !s.is_empty());
+		if exists("NVIM_LOG_FILE") && exists("NVIM") {
+			return Some(Self::Neovim);
+		}
+		// Emacs' `eat` draws into a buffer, where the probe would end up as text.
+		// `INSIDE_EMACS` is like `29.4,eat`
+		if var("INSIDE_EMACS").is_some_and(|s| s.split(',').any(|s| s == "eat")) {
+			return Some(Self::Eat);
 		}
 
 		let vars = [
@@ -70,36 +405,92 @@ impl Emulator {
 			("VSCODE_INJECTION", Self::VSCode),
 			("TABBY_CONFIG_DIRECTORY", Self::Tabby),
 		];
-		match vars.into_iter().find(|v| env_exists(v.0)) {
-			Some(var) => return var.1,
+		match vars.into_iter().find(|v| exists(v.0)) {
+			Some(var) => return Some(var.1),
 			None => warn!("[Adapter] No special environment variables detected"),
 		}
+		if var("TERMINAL_NAME").is_some_and(|s| s == "contour") {
+			return Some(Self::Contour);
+		}
 
-		let (term, program) = Self::via_env();
+		let (term, program) = Self::forwarded(&var, via_env());
 		match program.as_str() {
-			"iTerm.app" => return Self::Iterm2,
-			"WezTerm" => return Self::WezTerm,
-			"ghostty" => return Self::Ghostty,
-			"rio" => return Self::Rio,
-			"BlackBox" => return Self::BlackBox,
-			"vscode" => return Self::VSCode,
-			"Tabby" => return Self::Tabby,
-			"Hyper" => return Self::Hyper,
-			"mintty" => return Self::Mintty,
-			"Apple_Terminal" => return Self::Apple,
+			"iTerm.app" => return Some(Self::Iterm2),
+			"WezTerm" => return Some(Self::WezTerm),
+			"ghostty" => return Some(Self::Ghostty),
+			"rio" => return Some(Self::Rio),
+			"BlackBox" => return Some(Self::BlackBox),
+			"vscode" => return Some(Self::VSCode),
+			"Tabby" => return Some(Self::Tabby),
+			"Hyper" => return Some(Self::Hyper),
+			"mintty" => return Some(Self::Mintty),
+			"Apple_Terminal" => return Some(Self::Apple),
 			_ => warn!("[Adapter] Unknown TERM_PROGRAM: {program}"),
 		}
+		if Self::sixel_hint(&var) {
+			debug!("[Adapter] Sixel support forced by the environment");
+			return Some(Self::Unknown(vec![Adapter::Sixel]));
+		}
 		match term.as_str() {
-			"xterm-kitty" => return Self::Kitty,
-			"foot" => return Self::Foot,
-			"foot-extra" => return Self::Foot,
-			"xterm-ghostty" => return Self::Ghostty,
-			"rio" => return Self::Rio,
-			"rxvt-unicode-256color" => return Self::Urxvt,
+			"xterm-kitty" => return Some(Self::Kitty),
+			"foot" => return Some(Self::Foot),
+			"foot-extra" => return Some(Self::Foot),
+			"xterm-ghostty" => return Some(Self::Ghostty),
+			"rio" => return Some(Self::Rio),
+			"rxvt-unicode-256color" => return Some(Self::Urxvt),
+			"wayst" => return Some(Self::Wayst),
+			_ if Self::is_kitty_term(&term) => return Some(Self::Kitty),
+			_ if term.starts_with("contour") => return Some(Self::Contour),
 			_ => warn!("[Adapter] Unknown TERM: {term}"),
 		}
 
-		Self::via_csi().unwrap_or(Self::Unknown(vec![]))
+		// No escape sequences should be emitted at all for a dumb terminal
+		if term.is_empty() || term == "dumb" {
+			debug!("[Adapter] Dumb terminal detected (TERM={term:?}), skipping the CSI probe");
+			return Some(Self::Unknown(vec![]));
+		}
+
+		// Without `WT_Session` we're under a bare ConPTY, which supports no image
+		// protocol and may never answer the probe, stalling until the timeout.
+		if cfg!(windows) {
+			warn!("[Adapter] ConPTY without Windows Terminal detected, skipping the CSI probe");
+			return Some(Self::Unknown(vec![]));
+		}
+
+		None
+	}
+
+	/// Whether the user forced Sixel support for a terminal that isn't detected,
+	/// with either:
+	/// - `SIXEL` set to anything but empty or `0`, or
+	/// - `COLORTERM` listing `sixel`, separated by commas or spaces, e.g.
+	///   `truecolor,sixel`.
+	fn sixel_hint(var: impl Fn(&str) -> Option<String>) -> bool {
+		var("SIXEL").is_some_and(|s| !s.is_empty() && s != "0")
+			|| var("COLORTERM")
+				.is_some_and(|s| s.split([',', ' ']).any(|s| s.eq_ignore_ascii_case("sixel")))
+	}
+
+	/// Terminals sandboxed by Flatpak or Snap may not pass `TERM` or
+	/// `TERM_PROGRAM` through, so each of them that's empty falls back to the
+	/// one forwarded from the host:
+	/// - `HOST_TERM` for `TERM`, and
+	/// - `HOST_TERM_PROGRAM` for `TERM_PROGRAM`.
+	fn forwarded(
+		var: impl Fn(&str) -> Option<String>,
+		(term, program): (String, String),
+	) -> (String, String) {
+		let or_host = |s: String, name: &str| {
+			if !s.is_empty() {
+				return s;
+			}
+			let host = var(name).unwrap_or_default();
+			if !host.is_empty() {
+				debug!("[Adapter] Using {name}={host:?} forwarded from the host");
+			}
+			host
+		};
+		(or_host(term, "HOST_TERM"), or_host(program, "HOST_TERM_PROGRAM"))
 	}
 
 	pub fn via_env() -> (String, String) {
@@ -110,42 +501,463 @@ impl Emulator {
 		)
 	}
 
-	pub fn via_csi() -> Result<Self> {
+	// Matches TERM values like `kitty-direct` or `xterm-kitty-256color`,
+	// where "kitty" appears as a whole `-`-separated word.
+	fn is_kitty_term(term: &str) -> bool { term.split('-').any(|s| s == "kitty") }
+
+	pub fn via_csi() -> Result<Self> { Self::probe_csi().map(|p| p.emulator) }
+
+	/// Same as [`Self::via_csi`], along with how long the probe took.
+	pub fn probe_csi() -> Result<CsiProbe> {
+		Self::via_csi_on(&mut LineWriter::new(stderr()), Self::can_probe())
+	}
+
+	/// Whether queries can be written to the terminal, which takes both stdout
+	/// and stderr to be one: with either redirected, e.g. stdout piped into a
+	/// pager, the probe gets interleaved with the output. `YAZI_FORCE_PROBE=1`
+	/// overrides it.
+	pub fn can_probe() -> bool {
+		Self::can_probe_with(
+			stdout().is_terminal(),
+			stderr().is_terminal(),
+			std::env::var("YAZI_FORCE_PROBE").ok().as_deref(),
+		)
+	}
+
+	fn can_probe_with(stdout: bool, stderr: bool, force: Option<&str>) -> bool {
+		is_set(force) || (stdout && stderr)
+	}
+
+	// Probes through `w`, unless stdout or stderr isn't a terminal, e.g. redirected
+	// to a log file, where the probe would only end up as escapes in the file
+	fn via_csi_on(w: &mut impl Write, tty: bool) -> Result<CsiProbe> {
+		if !tty {
+			warn!("[Adapter] stdout or stderr is not a terminal, skipping the CSI probe");
+			return Ok(CsiProbe {
+				emulator:        Self::Unknown(vec![]),
+				sync_output:     false,
+				sixel_registers: None,
+				version:         None,
+				timing:          Default::default(),
+			});
+		}
+
+		// Keys reported by the Kitty keyboard protocol would mix into the replies
+		let keyboard = Self::keyboard_flags().is_some_and(|f| f > 0);
+
 		defer! { disable_raw_mode().ok(); }
+		let start = Instant::now();
 		enable_raw_mode()?;
+		let raw_mode = start.elapsed();
 
-		execute!(
-			LineWriter::new(stderr()),
-			SavePosition,
-			Print(Mux::csi("\x1b[>q\x1b_Gi=31,s=1,v=1,a=q,t=d,f=24;AAAA\x1b\\\x1b[c")),
-			RestorePosition
-		)?;
-
-		let resp = futures::executor::block_on(Self::read_until_da1());
-		let names = [
-			("kitty", Self::Kitty),
-			("Konsole", Self::Konsole),
-			("iTerm2", Self::Iterm2),
-			("WezTerm", Self::WezTerm),
-			("foot", Self::Foot),
-			("ghostty", Self::Ghostty),
-		];
+		let mut probe = Self::probe_guarded(w, *TMUX, keyboard, |w| {
+			futures::executor::block_on(Self::via_csi_from(
+				BufReader::new(tokio::io::stdin()),
+				|s| Self::quiet_probe(w, &Mux::csi(s)),
+				Self::probe_cancel(),
+			))
+		})?;
+		probe.timing.raw_mode = raw_mode;
+		SYNC_OUTPUT.set(probe.sync_output).ok();
+		SIXEL_REGISTERS.set(probe.sixel_registers).ok();
+		VERSION.set(probe.version.clone()).ok();
+		Ok(probe)
+	}
+
+	// Runs the probe `f` on `w`, with the Kitty keyboard protocol suspended if
+	// `keyboard`, and restores the terminal afterwards, also when `f` panics
+	// halfway through a probe, leaving the cursor moved and the text concealed
+	fn probe_guarded<W: Write, T>(
+		w: &mut W,
+		tmux: bool,
+		keyboard: bool,
+		f: impl FnOnce(&mut W) -> T,
+	) -> T {
+		if keyboard {
+			execute!(w, Print(Mux::csi_with("\x1b[>0u", tmux))).ok();
+		}
 
-		for (name, emulator) in names.iter() {
-			if resp.contains(name) {
-				return Ok(emulator.clone());
+		let mut w = scopeguard::guard(w, |w| {
+			if std::thread::panicking() {
+				execute!(w, RestorePosition, Print("\x1b[28m\x1b[?2026l")).ok();
+			}
+			if keyboard {
+				execute!(w, Print(Mux::csi_with("\x1b[<u", tmux))).ok();
 			}
+		});
+		f(&mut w)
+	}
+
+	// Writes a probe without flashing whatever the terminal fails to swallow:
+	// within a synchronized update, so the restored screen is all that's shown,
+	// and concealed, which hides anything echoed as text where that isn't
+	// supported. Whether it is isn't known yet, but the mode is ignored if not.
+	fn quiet_probe(w: &mut impl Write, s: &str) -> Result<()> {
+		let (begin, end) = ("\x1b[?2026h\x1b[8m", "\x1b[28m\x1b[?2026l");
+		Ok(execute!(w, Print(begin), SavePosition, Print(s), RestorePosition, Print(end))?)
+	}
+
+	// Send XTVERSION, the KGP query, and DA1 one after another, each followed by a
+	// DA1 so we know when its reply is complete, and classify what arrived.
+	// A terminal that ignores one query then only costs us that answer. DECRQM
+	// for synchronized output and XTSMGRAPHICS for the Sixel color registers
+	// ride along with XTVERSION.
+	async fn via_csi_from(
+		mut reader: impl AsyncRead + Unpin,
+		mut send: impl FnMut(&str) -> Result<()>,
+		cancel: &CancellationToken,
+	) -> Result<CsiProbe> {
+		let deadline = Instant::now() + Duration::from_secs(10);
+		let (mut resp, mut timing) = (String::new(), ProbeTiming::default());
+
+		macro_rules! exchange {
+			($s:expr) => {
+				Self::exchange(&mut reader, &mut send, $s, cancel, deadline, &mut timing).await?
+			};
+		}
+
+		resp += &exchange!("\x1b[?2026$p\x1b[?1;1;0S\x1b[>q\x1b[c");
+		if Self::from_xtversion(&resp).is_none() {
+			if !cancel.is_cancelled() {
+				resp += &exchange!("\x1b_Gi=31,s=1,v=1,a=q,t=d,f=24;AAAA\x1b\\\x1b[c");
+			}
+			if !cancel.is_cancelled() {
+				resp += &exchange!("\x1b[c");
+			}
+		}
+
+		Ok(CsiProbe {
+			emulator: Self::classify_csi(&resp),
+			sync_output: Self::parse_sync_output(&resp),
+			sixel_registers: Self::parse_sixel_registers(&resp),
+			version: Self::parse_xtversion(&resp).map(str::to_owned),
+			timing,
+		})
+	}
+
+	// Sends `s` and reads everything up to the DA1 reply following it, adding the
+	// time taken by each to `timing`
+	async fn exchange(
+		reader: &mut (impl AsyncRead + Unpin),
+		send: &mut impl FnMut(&str) -> Result<()>,
+		s: &str,
+		cancel: &CancellationToken,
+		deadline: Instant,
+		timing: &mut ProbeTiming,
+	) -> Result<String> {
+		let start = Instant::now();
+		send(s)?;
+		timing.write += start.elapsed();
+
+		let start = Instant::now();
+		let resp = Self::read_until_da1_from(reader, cancel, deadline).await;
+		timing.read += start.elapsed();
+		Ok(resp)
+	}
+
+	/// Classifies the replies of a terminal to the CSI probe: the emulator named
+	/// by its XTVERSION reply, otherwise the adapters it acknowledged.
+	pub fn classify_csi(resp: &str) -> Self {
+		if let Some(emulator) = Self::from_xtversion(resp) {
+			return emulator;
 		}
 
 		let mut adapters = Vec::with_capacity(2);
-		if resp.contains("\x1b_Gi=31;OK") {
+		if Self::kgp_ok(resp) {
 			adapters.push(Adapter::KgpOld);
 		}
-		if ["?4;", "?4c", ";4;", ";4c"].iter().any(|s| resp.contains(s)) {
+		if Self::da1_sixel(resp) {
 			adapters.push(Adapter::Sixel);
 		}
+		Self::Unknown(adapters)
+	}
+
+	// Whether the terminal acknowledged the KGP query, looked for in the whole
+	// response with the DA1 replies taken out, as a slow terminal may send one of
+	// them before the rest of the acknowledgement, which then arrives with the
+	// reply to the next query
+	pub(super) fn kgp_ok(resp: &str) -> bool {
+		let (mut stripped, mut rest) = (String::with_capacity(resp.len()), resp);
+		while let Some(i) = rest.find("\x1b[?") {
+			let tail = &rest[i + 3..];
+			let n = tail.bytes().take_while(|&b| b.is_ascii_digit() || b == b';').count();
+			if tail.as_bytes().get(n) == Some(&b'c') {
+				stripped.push_str(&rest[..i]);
+				rest = &tail[n + 1..];
+			} else {
+				stripped.push_str(&rest[..i + 3]);
+				rest = tail;
+			}
+		}
+		stripped.push_str(rest);
+		stripped.contains("\x1b_Gi=31;OK")
+	}
+
+	// Whether the DA1 reply lists Sixel graphics (attribute 4)
+	#[inline]
+	pub(super) fn da1_sixel(resp: &str) -> bool {
+		["?4;", "?4c", ";4;", ";4c"].iter().any(|s| resp.contains(s))
+	}
 
-		Ok(Self::Unknown(adapters))
+	fn from_xtversion(resp: &str) -> Option<Self> {
+		let name = Self::parse_xtversion(resp).unwrap_or(resp);
+		Self::XTVERSIONS.into_iter().find(|(s, _)| name.contains(s)).map(|(_, e)| e)
+	}
+
+	/// The `name version` reported by the emulator via XTVERSION (`CSI > q`).
+	/// Taken from the CSI probe, only queried on its own if that didn't run.
+	pub fn version_string() -> Option<String> {
+		VERSION
+			.get_or_init(|| {
+				Self::on_tty(|send| {
+					futures::executor::block_on(Self::xtversion_from(
+						BufReader::new(tokio::io::stdin()),
+						send,
+						Self::probe_cancel(),
+						*TMUX,
+					))
+				})
+			})
+			.clone()
+	}
+
+	// Sends XTVERSION followed by a DA1 until it's answered. tmux often drops the
+	// reply to the first one passed through, so it's tried up to 3 times there,
+	// with a growing pause in between, all within the time a single query gets.
+	async fn xtversion_from(
+		mut reader: impl AsyncRead + Unpin,
+		mut send: impl FnMut(&str) -> Result<()>,
+		cancel: &CancellationToken,
+		tmux: bool,
+	) -> Option<String> {
+		let deadline = Instant::now() + Duration::from_secs(10);
+		for i in 0..if tmux { 3 } else { 1 } {
+			if i > 0 {
+				debug!("[Adapter] XTVERSION not answered, retrying ({i})");
+				tokio::time::sleep_until(deadline.min(Instant::now() + Duration::from_millis(50 << i)))
+					.await;
+			}
+
+			send("\x1b[>q\x1b[c").ok()?;
+			let resp = Self::read_until_da1_from(&mut reader, cancel, deadline).await;
+			if let Some(version) = Self::parse_xtversion(&resp) {
+				return Some(version.to_owned());
+			} else if cancel.is_cancelled() || Instant::now() >= deadline {
+				break;
+			}
+		}
+		None
+	}
+
+	/// Whether synchronized output (DECSET 2026) is supported, so frames can be
+	/// wrapped in BSU/ESU to avoid flicker. `false` if the terminal doesn't tell.
+	/// Taken from the CSI probe, only queried on its own if that didn't run.
+	pub fn supports_sync_output(&self) -> bool {
+		*SYNC_OUTPUT
+			.get_or_init(|| Self::query("\x1b[?2026$p").is_some_and(|s| Self::parse_sync_output(&s)))
+	}
+
+	// A DECRPM reply `CSI ? 2026 ; Ps $ y`, where 1 and 2 mean set and reset,
+	// 3 permanently set, and 0 and 4 not recognized and permanently reset
+	fn parse_sync_output(resp: &str) -> bool {
+		resp
+			.split_once("\x1b[?2026;")
+			.and_then(|(_, s)| s.split_once("$y"))
+			.is_some_and(|(ps, _)| matches!(ps, "1" | "2" | "3"))
+	}
+
+	/// Whether the Kitty keyboard protocol is supported, i.e. the terminal
+	/// answers its `CSI ? u` query, only queried the first time.
+	pub fn kitty_keyboard(&self) -> bool { Self::keyboard_flags().is_some() }
+
+	// The progressive enhancement flags currently in effect, 0 if none is
+	fn keyboard_flags() -> Option<u16> {
+		static FLAGS: OnceLock<Option<u16>> = OnceLock::new();
+		*FLAGS.get_or_init(|| Self::parse_keyboard_flags(&Self::query("\x1b[?u")?))
+	}
+
+	// A `CSI ? flags u` reply
+	fn parse_keyboard_flags(resp: &str) -> Option<u16> {
+		resp.split("\x1b[?").skip(1).find_map(|s| s.split_once('u')?.0.parse().ok())
+	}
+
+	/// The background color as reported to OSC 11, `None` if unanswered.
+	pub fn bg_color() -> Option<(u8, u8, u8)> {
+		static BG: OnceLock<Option<(u8, u8, u8)>> = OnceLock::new();
+		*BG.get_or_init(|| Self::parse_bg_color(&Self::query("\x1b]11;?\x1b\\")?))
+	}
+
+	/// Whether the terminal has a dark background, judged by [`Self::bg_color`],
+	/// or the `COLORFGBG` hint set by some terminals if it isn't answered.
+	/// Dark if neither tells.
+	pub fn is_dark_background() -> bool {
+		Self::dark_background(Self::bg_color(), std::env::var("COLORFGBG").ok().as_deref())
+	}
+
+	fn dark_background(bg: Option<(u8, u8, u8)>, colorfgbg: Option<&str>) -> bool {
+		if let Some((r, g, b)) = bg {
+			return 299 * u32::from(r) + 587 * u32::from(g) + 114 * u32::from(b) < 128_000;
+		}
+
+		// `fg;bg`, or `fg;default;bg` by rxvt, the background being one of the 16
+		// ANSI colors, of which 7 (white) and 9-15 (the bright ones but gray) are
+		// light ones
+		let bg = colorfgbg.and_then(|s| s.rsplit(';').next()).and_then(|s| s.trim().parse::<u8>().ok());
+		!matches!(bg, Some(7 | 9..=15))
+	}
+
+	// An `OSC 11 ; rgb:RRRR/GGGG/BBBB` reply, with 1 to 4 hex digits per channel,
+	// terminated by either BEL or ST
+	fn parse_bg_color(resp: &str) -> Option<(u8, u8, u8)> {
+		let (_, s) = resp.split_once("\x1b]11;rgb:")?;
+		let s = &s[..s.find(['\x07', '\x1b'])?];
+
+		let mut it = s.split('/').map(|c| {
+			let v = u32::from_str_radix(c, 16).ok().filter(|_| (1..=4).contains(&c.len()))?;
+			Some((v * 255 / ((1 << (4 * c.len())) - 1)) as u8)
+		});
+		match (it.next()??, it.next()??, it.next()??, it.next()) {
+			(r, g, b, None) => Some((r, g, b)),
+			_ => None,
+		}
+	}
+
+	/// Whether the geometry queries, like [`Self::pixel_size`], are sent. Under
+	/// Neovim only with `YAZI_NVIM_GEOMETRY=1`, for plugins drawing images
+	/// through its own APIs, as the queries can disrupt TUIs running in its
+	/// terminal. Its adapters stay empty.
+	pub fn queries_geometry(&self) -> bool {
+		self.queries_geometry_with(std::env::var("YAZI_NVIM_GEOMETRY").ok().as_deref())
+	}
+
+	fn queries_geometry_with(&self, nvim_geometry: Option<&str>) -> bool {
+		*self != Self::Neovim || is_set(nvim_geometry)
+	}
+
+	#[inline]
+	fn geometry<T>(&self, f: impl FnOnce() -> Option<T>) -> Option<T> {
+		if self.queries_geometry() { f() } else { None }
+	}
+
+	/// The `(width, height)` of the terminal's text area in pixels, as reported
+	/// to `CSI 14 t`. `None` if unreported, and always under tmux, which would
+	/// report the size of its own.
+	pub fn pixel_size(&self) -> Option<(u16, u16)> {
+		self.geometry(|| Self::pixel_size_via(*TMUX, || Self::query("\x1b[14t")))
+	}
+
+	fn pixel_size_via(tmux: bool, query: impl FnOnce() -> Option<String>) -> Option<(u16, u16)> {
+		if tmux {
+			warn!("[Adapter] Terminal pixel size is unavailable under tmux");
+			return None;
+		}
+		Self::parse_pixel_size(&query()?)
+	}
+
+	// A `CSI 4 ; height ; width t` reply
+	fn parse_pixel_size(resp: &str) -> Option<(u16, u16)> {
+		let (_, s) = resp.split_once("\x1b[4;")?;
+		let (h, w) = s.split_once('t')?.0.split_once(';')?;
+		match (w.parse().ok()?, h.parse().ok()?) {
+			(0, _) | (_, 0) => None,
+			size => Some(size),
+		}
+	}
+
+	/// The `(columns, rows)` of the terminal's text area in cells, as reported to
+	/// `CSI 18 t`, to check the layout against when the pixel size isn't
+	/// reported. `None` if unreported, and always under tmux, whose passthrough
+	/// would reach the outer terminal.
+	pub fn text_area_cells(&self) -> Option<(u16, u16)> {
+		self.geometry(|| Self::text_area_cells_via(*TMUX, || Self::query("\x1b[18t")))
+	}
+
+	/// The `(width, height)` of a cell in pixels, from [`Self::pixel_size`] and
+	/// [`Self::text_area_cells`]. `None` if either is unreported.
+	pub fn cell_size(&self) -> Option<(u16, u16)> {
+		self.geometry(|| {
+			Self::cell_size_from(
+				Self::pixel_size_via(*TMUX, || Self::query("\x1b[14t"))?,
+				Self::text_area_cells_via(*TMUX, || Self::query("\x1b[18t"))?,
+			)
+		})
+	}
+
+	fn cell_size_from((width, height): (u16, u16), (cols, rows): (u16, u16)) -> Option<(u16, u16)> {
+		match (width / cols, height / rows) {
+			(0, _) | (_, 0) => None,
+			size => Some(size),
+		}
+	}
+
+	fn text_area_cells_via(tmux: bool, query: impl FnOnce() -> Option<String>) -> Option<(u16, u16)> {
+		if tmux {
+			warn!("[Adapter] Terminal text area size is unavailable under tmux");
+			return None;
+		}
+		Self::parse_text_area_cells(&query()?)
+	}
+
+	// A `CSI 8 ; rows ; cols t` reply
+	fn parse_text_area_cells(resp: &str) -> Option<(u16, u16)> {
+		let (_, s) = resp.split_once("\x1b[8;")?;
+		let (rows, cols) = s.split_once('t')?.0.split_once(';')?;
+		match (cols.parse().ok()?, rows.parse().ok()?) {
+			(0, _) | (_, 0) => None,
+			size => Some(size),
+		}
+	}
+
+	// Sends a query followed by DA1, and returns everything up to its reply
+	pub(super) fn query(s: &str) -> Option<String> {
+		Self::on_tty(|send| {
+			send(&format!("{s}\x1b[c")).ok()?;
+			Some(futures::executor::block_on(Self::read_until_da1()))
+		})
+	}
+
+	// Runs `f` with the terminal in raw mode, passing it a function that sends
+	// a query through tmux if needed. `None` if the terminal can't be probed.
+	fn on_tty<T>(f: impl FnOnce(fn(&str) -> Result<()>) -> Option<T>) -> Option<T> {
+		if !Self::can_probe() {
+			return None;
+		}
+
+		defer! { disable_raw_mode().ok(); }
+		enable_raw_mode().ok()?;
+
+		f(|s| {
+			Ok(execute!(LineWriter::new(stderr()), SavePosition, Print(Mux::csi(s)), RestorePosition)?)
+		})
+	}
+
+	// Extracts the payload of a `DCS > | name version ST` reply
+	fn parse_xtversion(resp: &str) -> Option<&str> {
+		let (_, s) = resp.split_once("\x1bP>|")?;
+		s.split_once("\x1b\\").map(|(s, _)| s.trim())
+	}
+
+	/// Whether the cursor position may get out of sync after drawing images,
+	/// so that [`Self::move_lock`] has to move the cursor repeatedly.
+	pub fn needs_cursor_resync(&self) -> bool {
+		self.cursor_resync(*TMUX, std::env::var("KONSOLE_VERSION").ok().as_deref())
+	}
+
+	// I really don't want to add this,
+	// But tmux, ConPTY, and Konsole before 22.04 sometimes cause the cursor
+	// position to get out of sync.
+	#[allow(clippy::unnecessary_map_or)] // `is_none_or()` needs Rust 1.82
+	fn cursor_resync(&self, tmux: bool, konsole_version: Option<&str>) -> bool {
+		if tmux || cfg!(windows) {
+			return true;
+		}
+		match self {
+			// `KONSOLE_VERSION` is like `220803` for 22.08.3
+			Self::Konsole => {
+				konsole_version.and_then(|v| v.parse::<u32>().ok()).map_or(true, |v| v < 220400)
+			}
+			_ => false,
+		}
 	}
 
 	pub fn move_lock<F, T>((x, y): (u16, u16), cb: F) -> Result<T>
@@ -158,9 +970,8 @@ impl Emulator {
 
 		let mut buf = std::io::BufWriter::new(stderr().lock());
 
-		// I really don't want to add this,
-		// But tmux and ConPTY sometimes cause the cursor position to get out of sync.
-		if *TMUX || cfg!(windows) {
+		let resync = EMULATOR.needs_cursor_resync();
+		if resync {
 			execute!(buf, SavePosition, MoveTo(x, y), Show)?;
 			execute!(buf, MoveTo(x, y), Show)?;
 			execute!(buf, MoveTo(x, y), Show)?;
@@ -170,7 +981,7 @@ impl Emulator {
 		}
 
 		let result = cb(&mut buf);
-		if *TMUX || cfg!(windows) {
+		if resync {
 			queue!(buf, Hide, RestorePosition)?;
 		} else {
 			queue!(buf, RestorePosition)?;
@@ -181,30 +992,978 @@ impl Emulator {
 	}
 
 	pub async fn read_until_da1() -> String {
+		Self::read_until_da1_from(
+			&mut BufReader::new(tokio::io::stdin()),
+			Self::probe_cancel(),
+			Instant::now() + Duration::from_secs(10),
+		)
+		.await
+	}
+
+	/// Cancels any ongoing and future [`Self::read_until_da1`] promptly,
+	/// e.g. on shutdown, so an unresponsive terminal can't hold the process up.
+	pub fn cancel_probe() { Self::probe_cancel().cancel(); }
+
+	#[inline]
+	pub fn probe_cancelled() -> bool { Self::probe_cancel().is_cancelled() }
+
+	fn probe_cancel() -> &'static CancellationToken {
+		static CANCEL: OnceLock<CancellationToken> = OnceLock::new();
+		CANCEL.get_or_init(CancellationToken::new)
+	}
+
+	pub(super) async fn read_until_da1_from(
+		reader: &mut (impl AsyncRead + Unpin),
+		cancel: &CancellationToken,
+		deadline: Instant,
+	) -> String {
 		let mut buf: Vec<u8> = Vec::with_capacity(200);
 		let read = async {
-			let mut stdin = BufReader::new(tokio::io::stdin());
 			loop {
 				let mut c = [0; 1];
-				if stdin.read(&mut c).await? == 0 {
+				if reader.read(&mut c).await? == 0 {
 					bail!("unexpected EOF");
 				}
 				buf.push(c[0]);
-				if c[0] != b'c' || !buf.contains(&0x1b) {
-					continue;
-				}
-				if buf.rsplitn(2, |&b| b == 0x1b).next().is_some_and(|s| s.starts_with(b"[?")) {
+				if c[0] == b'c' && Self::ends_with_da1(&buf) {
 					break;
 				}
 			}
 			Ok(())
 		};
 
-		match timeout(Duration::from_secs(10), read).await {
-			Err(e) => error!("read_until_da1 timed out: {buf:?}, error: {e:?}"),
-			Ok(Err(e)) => error!("read_until_da1 failed: {buf:?}, error: {e:?}"),
-			Ok(Ok(())) => {}
+		let result = select! {
+			_ = cancel.cancelled() => None,
+			r = timeout_at(deadline, read) => Some(r),
+		};
+		match result {
+			None => warn!("read_until_da1 cancelled: {buf:?}"),
+			Some(Err(e)) => error!("read_until_da1 timed out: {buf:?}, error: {e:?}"),
+			Some(Ok(Err(e))) => error!("read_until_da1 failed: {buf:?}, error: {e:?}"),
+			Some(Ok(Ok(()))) => {}
+		}
+		Self::from_c1(&buf)
+	}
+
+	// Whether the last control sequence is a DA1 reply `CSI ? ... c`,
+	// with CSI being either `ESC [` or the 8-bit 0x9b
+	fn ends_with_da1(buf: &[u8]) -> bool {
+		match buf.iter().rposition(|&b| b == 0x1b || b == 0x9b) {
+			Some(i) if buf[i] == 0x1b => buf[i + 1..].starts_with(b"[?"),
+			Some(i) => buf[i + 1..].starts_with(b"?"),
+			None => false,
+		}
+	}
+
+	// Converts the 8-bit C1 controls some terminals reply with to their 7-bit
+	// forms, so replies parse the same way. Bytes of valid UTF-8 are kept.
+	fn from_c1(buf: &[u8]) -> String {
+		let (mut s, mut rest) = (String::with_capacity(buf.len()), buf);
+		while let Err(e) = std::str::from_utf8(rest) {
+			let (valid, after) = rest.split_at(e.valid_up_to());
+			s.push_str(std::str::from_utf8(valid).unwrap_or_default());
+
+			let (invalid, after) = after.split_at(e.error_len().unwrap_or(after.len()));
+			for &b in invalid {
+				s.push_str(match b {
+					0x90 => "\x1bP",
+					0x9b => "\x1b[",
+					0x9c => "\x1b\\",
+					0x9d => "\x1b]",
+					0x9f => "\x1b_",
+					_ => "\u{fffd}",
+				});
+			}
+			rest = after;
+		}
+		s.push_str(std::str::from_utf8(rest).unwrap_or_default());
+		s
+	}
+}
+
+// Whether an opt-in variable like `YAZI_FORCE_PROBE` is set to a truthy value
+fn is_set(var: Option<&str>) -> bool { matches!(var.map(str::trim), Some("1" | "true" | "yes")) }
+
+// Parses the leading `major.minor.patch` of a version string, ignoring any
+// suffix like `beta1`; missing components are treated as 0.
+fn parse_version(s: &str) -> Option<(u32, u32, u32)> {
+	let mut it = s.trim().split('.').map(|p| {
+		let n = p.bytes().take_while(u8::is_ascii_digit).count();
+		p[..n].parse().ok()
+	});
+
+	let major = it.next().flatten()?;
+	Some((major, it.next().flatten().unwrap_or(0), it.next().flatten().unwrap_or(0)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn vars<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'a {
+		|name| vars.iter().find(|(k, _)| *k == name).map(|(_, v)| v.to_string())
+	}
+
+	fn env(term: &str, program: &str) -> impl FnOnce() -> (String, String) {
+		let (term, program) = (term.to_owned(), program.to_owned());
+		move || (term, program)
+	}
+
+	#[test]
+	fn test_forwarded_from_host() {
+		let cases = [
+			(&[("HOST_TERM", "xterm-kitty")][..], env("", ""), Some(Emulator::Kitty)),
+			(&[("HOST_TERM_PROGRAM", "WezTerm")], env("", ""), Some(Emulator::WezTerm)),
+			(
+				&[("HOST_TERM", "foot"), ("HOST_TERM_PROGRAM", "ghostty")],
+				env("", ""),
+				Some(Emulator::Ghostty),
+			),
+			// Only used in place of the empty ones
+			(&[("HOST_TERM", "xterm-kitty")], env("foot", ""), Some(Emulator::Foot)),
+			(&[("HOST_TERM_PROGRAM", "WezTerm")], env("xterm-256color", "rio"), Some(Emulator::Rio)),
+			(&[("HOST_TERM", "xterm-256color")], env("", ""), None),
+			(&[("HOST_TERM", "")], env("", ""), Some(Emulator::Unknown(vec![]))),
+		];
+		for (i, (v, env, expected)) in cases.into_iter().enumerate() {
+			assert_eq!(Emulator::via_vars(vars(v), env), expected, "case {i}");
+		}
+	}
+
+	#[test]
+	fn test_kitty_term() {
+		for term in ["xterm-kitty", "kitty", "kitty-direct", "xterm-kitty-256color"] {
+			assert!(Emulator::is_kitty_term(term), "{term}");
+		}
+		for term in ["kittyfoo", "xterm-kittyfoo", "foo-kittens", "xterm-256color", ""] {
+			assert!(!Emulator::is_kitty_term(term), "{term}");
+		}
+	}
+
+	#[test]
+	fn test_via_vars() {
+		let kitty = [("KITTY_WINDOW_ID", "1"), ("WT_Session", "1")];
+		assert_eq!(Emulator::via_vars(vars(&kitty), env("", "")), Some(Emulator::Kitty));
+		assert_eq!(
+			Emulator::via_vars(vars(&[("KITTY_WINDOW_ID", "")]), env("foot", "")),
+			Some(Emulator::Foot)
+		);
+		assert_eq!(Emulator::via_vars(vars(&[]), env("kitty-direct", "")), Some(Emulator::Kitty));
+		assert_eq!(Emulator::via_vars(vars(&[]), env("", "WezTerm")), Some(Emulator::WezTerm));
+
+		#[cfg(unix)]
+		assert_eq!(Emulator::via_vars(vars(&[]), env("xterm-256color", "")), None);
+	}
+
+	#[test]
+	fn test_sixel_hint() {
+		let unknown = |v: &[(&str, &str)]| Emulator::via_vars(vars(v), env("xterm-256color", ""));
+		let sixel = Some(Emulator::Unknown(vec![Adapter::Sixel]));
+
+		assert_eq!(unknown(&[("COLORTERM", "sixel")]), sixel);
+		assert_eq!(unknown(&[("COLORTERM", "truecolor,SIXEL")]), sixel);
+		assert_eq!(unknown(&[("COLORTERM", "truecolor sixel")]), sixel);
+		assert_eq!(unknown(&[("SIXEL", "1")]), sixel);
+		#[cfg(unix)]
+		{
+			assert_eq!(unknown(&[("COLORTERM", "truecolor")]), None);
+			assert_eq!(unknown(&[("COLORTERM", "nosixel")]), None);
+			assert_eq!(unknown(&[("SIXEL", "0")]), None);
+			assert_eq!(unknown(&[("SIXEL", "")]), None);
+		}
+
+		// Detected terminals are unaffected
+		let hint = [("COLORTERM", "sixel")];
+		assert_eq!(Emulator::via_vars(vars(&hint), env("", "WezTerm")), Some(Emulator::WezTerm));
+		assert_eq!(
+			Emulator::via_vars(vars(&[("KITTY_WINDOW_ID", "1"), hint[0]]), env("", "")),
+			Some(Emulator::Kitty)
+		);
+	}
+
+	#[test]
+	fn test_contour() {
+		// Environment only
+		assert_eq!(Emulator::via_vars(vars(&[]), env("contour-latest", "")), Some(Emulator::Contour));
+		assert_eq!(Emulator::via_vars(vars(&[]), env("contour", "")), Some(Emulator::Contour));
+		assert_eq!(
+			Emulator::via_vars(vars(&[("TERMINAL_NAME", "contour")]), env("xterm-256color", "")),
+			Some(Emulator::Contour)
+		);
+
+		// Confirmed, or the CSI probe couldn't tell
+		assert_eq!(Emulator::merge(Emulator::Contour, Some(Emulator::Contour)), Emulator::Contour);
+		assert_eq!(
+			Emulator::merge(Emulator::Contour, Some(Emulator::Unknown(vec![]))),
+			Emulator::Contour
+		);
+		assert_eq!(
+			Emulator::merge(Emulator::Contour, Some(Emulator::Unknown(vec![Adapter::Sixel]))),
+			Emulator::Contour
+		);
+		assert_eq!(Emulator::merge(Emulator::Contour, None), Emulator::Contour);
+
+		// Conflicting, the CSI probe wins
+		assert_eq!(Emulator::merge(Emulator::Contour, Some(Emulator::Kitty)), Emulator::Kitty);
+		assert_eq!(
+			Emulator::merge(Emulator::Contour, Some(Emulator::Unknown(vec![Adapter::KgpOld]))),
+			Emulator::Unknown(vec![Adapter::KgpOld])
+		);
+	}
+
+	#[tokio::test]
+	async fn test_contour_csi() {
+		let e = via_csi(&["\x1bP>|contour 0.4.3.6442\x1b\\\x1b[?65;1;4;6c"]).await;
+		assert_eq!(e, Emulator::Contour);
+	}
+
+	#[test]
+	fn test_from_xtversion() {
+		let e = |s: &str| Emulator::from_xtversion(s);
+		assert_eq!(e("\x1bP>|kitty(0.36.4)\x1b\\\x1b[?62c"), Some(Emulator::Kitty));
+		assert_eq!(e("\x1bP>|WezTerm 20240203-110809-5046fc22\x1b\\"), Some(Emulator::WezTerm));
+		assert_eq!(e("\x1bP>|bobcat 0.9.1\x1b\\\x1b[?62;4c"), Some(Emulator::Bobcat));
+		assert_eq!(e("\x1bP>|XTerm(390)\x1b\\\x1b[?62;4c"), None);
+		assert_eq!(e("\x1b[?62;4c"), None);
+
+		for (name, emulator) in Emulator::XTVERSIONS {
+			assert_eq!(e(&format!("\x1bP>|{name} 1.0\x1b\\")), Some(emulator.clone()));
+			assert!(!emulator.adapters().is_empty());
+		}
+	}
+
+	#[test]
+	fn test_classify_csi() {
+		use Adapter::*;
+		let cases = [
+			("\x1bP>|kitty(0.36.4)\x1b\\\x1b[?62;c", Emulator::Kitty),
+			("\x1bP>|WezTerm 20240203-110809-5046fc22\x1b\\\x1b[?65;4;6;18;22c", Emulator::WezTerm),
+			("\x1bP>|foot(1.18.1)\x1b\\\x1b[?62;4;22c", Emulator::Foot),
+			("\x1bP>|ghostty 1.1.3\x1b\\\x1b[?62;22c", Emulator::Ghostty),
+			("\x1bP>|Konsole 24.08.3\x1b\\\x1b[?62;22c", Emulator::Konsole),
+			("\x1bP>|iTerm2 3.5.10\x1b\\\x1b[?62;4c", Emulator::Iterm2),
+			// xterm names itself, but isn't known, so it's told by its DA1
+			("\x1bP>|XTerm(390)\x1b\\\x1b[?64;1;2;4;6;9;15;18;21;22c", Emulator::Unknown(vec![Sixel])),
+			("\x1b[?62;c\x1b_Gi=31;OK\x1b\\\x1b[?62;c\x1b[?62;c", Emulator::Unknown(vec![KgpOld])),
+			("\x1b[?62;4c\x1b_Gi=31;OK\x1b\\\x1b[?62;4c", Emulator::Unknown(vec![KgpOld, Sixel])),
+			("\x1b_Gi=31;ENOTSUPPORTED:\x1b\\\x1b[?1;2c", Emulator::Unknown(vec![])),
+			// The DA1 reply came in the middle of the KGP one
+			(
+				"\x1b[?62;4c\x1b_Gi=31;\x1b[?62;4cOK\x1b\\\x1b[?62;4c",
+				Emulator::Unknown(vec![KgpOld, Sixel]),
+			),
+			("\x1b[?62c\x1b_Gi=31\x1b[?62c;OK\x1b\\", Emulator::Unknown(vec![KgpOld])),
+			("\x1b_Gi=31;\x1b[?62;4cNOTOK\x1b\\", Emulator::Unknown(vec![Sixel])),
+			("\x1b[?1;2c", Emulator::Unknown(vec![])),
+			("", Emulator::Unknown(vec![])),
+		];
+		for (resp, expected) in cases {
+			assert_eq!(Emulator::classify_csi(resp), expected, "{resp:?}");
+		}
+	}
+
+	#[tokio::test]
+	async fn test_bobcat() {
+		let e = via_csi(&["\x1bP>|bobcat 0.9.1\x1b\\\x1b[?62;4c"]).await;
+		assert_eq!(e.adapters(), vec![Adapter::Kgp, Adapter::Sixel]);
+	}
+
+	#[tokio::test]
+	async fn test_wayst() {
+		let e = via_csi(&["\x1bP>|wayst 0.1.0\x1b\\\x1b[?62;4c"]).await;
+		assert_eq!(e, Emulator::Wayst);
+		assert_eq!(e.adapters(), vec![Adapter::Sixel]);
+
+		assert_eq!(Emulator::via_vars(vars(&[]), env("wayst", "")), Some(Emulator::Wayst));
+	}
+
+	#[test]
+	fn test_apple() {
+		// Told by `TERM_PROGRAM` alone, so the CSI probe isn't sent
+		let e = Emulator::via_vars(vars(&[]), env("xterm-256color", "Apple_Terminal"));
+		assert_eq!(e, Some(Emulator::Apple));
+
+		assert!(!Emulator::Apple.supports_images());
+		assert!(!Emulator::Apple.truecolor_with(Some("truecolor")));
+	}
+
+	#[test]
+	fn test_truecolor() {
+		assert!(Emulator::Kitty.truecolor_with(None));
+		assert!(Emulator::Unknown(vec![]).truecolor_with(Some("truecolor")));
+		assert!(Emulator::Unknown(vec![]).truecolor_with(Some("24bit")));
+		assert!(!Emulator::Unknown(vec![Adapter::Sixel]).truecolor_with(Some("256color")));
+		assert!(!Emulator::Unknown(vec![]).truecolor_with(None));
+
+		assert!(Emulator::Kitty.supports_images());
+		assert!(!Emulator::Neovim.supports_images());
+	}
+
+	#[test]
+	fn test_supports_hyperlinks() {
+		for e in
+			[Emulator::Kitty, Emulator::WezTerm, Emulator::Foot, Emulator::Ghostty, Emulator::Iterm2]
+		{
+			assert!(e.hyperlinks_with(None), "{e:?}");
+		}
+		for e in [Emulator::Apple, Emulator::Urxvt, Emulator::Neovim, Emulator::Eat] {
+			assert!(!e.hyperlinks_with(Some("7600")), "{e:?}");
+		}
+
+		// Unknown ones, unless they're VTE 0.50 or later
+		let unknown = Emulator::Unknown(vec![]);
+		assert!(unknown.hyperlinks_with(Some("7600")));
+		assert!(unknown.hyperlinks_with(Some("5000")));
+		assert!(!unknown.hyperlinks_with(Some("4803")));
+		assert!(!unknown.hyperlinks_with(Some("garbage")));
+		assert!(!unknown.hyperlinks_with(None));
+	}
+
+	#[test]
+	fn test_eat() {
+		// Decided before the terminal Emacs runs in, and without looking at TERM
+		let probe = || panic!("TERM shouldn't be needed");
+		let e =
+			Emulator::via_vars(vars(&[("INSIDE_EMACS", "29.4,eat"), ("KITTY_WINDOW_ID", "1")]), probe);
+		assert_eq!(e, Some(Emulator::Eat));
+		assert_eq!(Emulator::Eat.adapters(), []);
+
+		// Other Emacs terminals, or `eat` only as part of another word
+		for s in ["29.4,vterm", "29.4,comint", "29.4,heat"] {
+			let e = Emulator::via_vars(vars(&[("INSIDE_EMACS", s)]), env("xterm-256color", ""));
+			assert_eq!(e, None, "{s}");
+		}
+	}
+
+	#[test]
+	fn test_dumb_term() {
+		assert_eq!(Emulator::via_vars(vars(&[]), env("dumb", "")), Some(Emulator::Unknown(vec![])));
+		assert_eq!(Emulator::via_vars(vars(&[]), env("", "")), Some(Emulator::Unknown(vec![])));
+		assert_eq!(
+			Emulator::via_vars(vars(&[("KITTY_WINDOW_ID", "1")]), env("dumb", "")),
+			Some(Emulator::Kitty)
+		);
+	}
+
+	#[test]
+	#[cfg(windows)]
+	fn test_conpty() {
+		assert_eq!(
+			Emulator::via_vars(vars(&[]), env("xterm-256color", "")),
+			Some(Emulator::Unknown(vec![]))
+		);
+		assert_eq!(
+			Emulator::via_vars(vars(&[("WT_Session", "1")]), env("xterm-256color", "")),
+			Some(Emulator::Microsoft)
+		);
+	}
+
+	#[test]
+	fn test_parse_version() {
+		assert_eq!(parse_version("3.6.0beta1"), Some((3, 6, 0)));
+		assert_eq!(parse_version("1.2"), Some((1, 2, 0)));
+		assert_eq!(parse_version(" 20240203-110809-5046fc22 "), Some((20240203, 0, 0)));
+		assert_eq!(parse_version("v1.0"), None);
+		assert_eq!(parse_version(""), None);
+	}
+
+	#[test]
+	fn test_sixel_palette_size() {
+		let e = |s: &str| Emulator::palette_size(Emulator::parse_sixel_registers(s));
+		assert_eq!(e("\x1b[?1;0;16S\x1b[?62;4c"), 16);
+		assert_eq!(e("\x1b[?1;0;256S"), 256);
+		assert_eq!(e("\x1b[?1;0;1024S"), 256);
+		assert_eq!(e("\x1b[?1;0;0S"), 256);
+		// Failed or unanswered
+		assert_eq!(e("\x1b[?1;3;0S"), 256);
+		assert_eq!(e("\x1b[?62;4c"), 256);
+	}
+
+	#[test]
+	fn test_parse_xtversion() {
+		assert_eq!(Emulator::parse_xtversion("\x1bP>|kitty(0.36.4)\x1b\\"), Some("kitty(0.36.4)"));
+		assert_eq!(
+			Emulator::parse_xtversion("\x1b[?2026;2$y\x1bP>|WezTerm 20240203 \x1b\\\x1b[?65;4c"),
+			Some("WezTerm 20240203")
+		);
+		assert_eq!(Emulator::parse_xtversion("\x1bP>|foot(1.18.1)"), None);
+		assert_eq!(Emulator::parse_xtversion("\x1bP1$r0m\x1b\\"), None);
+	}
+
+	#[test]
+	fn test_microsoft_sixel() {
+		assert_eq!(Emulator::microsoft_adapters(Some("Windows Terminal 1.21.3231.0")), vec![]);
+		assert_eq!(Emulator::microsoft_adapters(Some("Windows Terminal 1.18.10301.0")), vec![]);
+		assert_eq!(Emulator::microsoft_adapters(Some("Windows Terminal 1.22.2362.0")), vec![
+			Adapter::Sixel
+		]);
+		assert_eq!(Emulator::microsoft_adapters(Some("Windows Terminal 1.23.10353.0")), vec![
+			Adapter::Sixel
+		]);
+		assert_eq!(Emulator::microsoft_adapters(None), vec![Adapter::Sixel]);
+	}
+
+	#[test]
+	fn test_iterm2_kgp() {
+		let resp = "\x1bP>|iTerm2 3.6.0beta2\x1b\\\x1b[?62;4c";
+		assert_eq!(Emulator::parse_xtversion(resp), Some("iTerm2 3.6.0beta2"));
+		assert_eq!(Emulator::parse_xtversion("\x1b[?62;4c"), None);
+
+		assert_eq!(Emulator::iterm2_adapters(Emulator::parse_xtversion(resp)), vec![
+			Adapter::Kgp,
+			Adapter::Iip,
+			Adapter::Sixel
+		]);
+		assert_eq!(Emulator::iterm2_adapters(Some("iTerm2 3.5.11")), vec![
+			Adapter::Iip,
+			Adapter::Sixel
+		]);
+		assert_eq!(Emulator::iterm2_adapters(None), vec![Adapter::Iip, Adapter::Sixel]);
+	}
+
+	#[test]
+	fn test_vscode_sixel() {
+		assert_eq!(Emulator::vscode_adapters(Some("1.82.2")), vec![Adapter::Iip]);
+		assert_eq!(Emulator::vscode_adapters(Some("1.86.0")), vec![Adapter::Iip, Adapter::Sixel]);
+		assert_eq!(Emulator::vscode_adapters(Some("1.95.0-insider")), vec![
+			Adapter::Iip,
+			Adapter::Sixel
+		]);
+		assert_eq!(Emulator::vscode_adapters(None), vec![Adapter::Iip, Adapter::Sixel]);
+	}
+
+	#[test]
+	#[cfg(not(target_os = "macos"))]
+	fn test_ghostty_sixel() {
+		assert_eq!(Emulator::ghostty_adapters(Some("1.1.3")), vec![Adapter::Kgp]);
+		assert_eq!(Emulator::ghostty_adapters(Some("1.2.0")), vec![Adapter::Kgp, Adapter::Sixel]);
+		assert_eq!(Emulator::ghostty_adapters(Some("1.2.1-main+a1b2c3")), vec![
+			Adapter::Kgp,
+			Adapter::Sixel
+		]);
+		assert_eq!(Emulator::ghostty_adapters(None), vec![Adapter::Kgp]);
+	}
+
+	#[test]
+	#[cfg(target_os = "macos")]
+	fn test_ghostty_sixel() {
+		assert_eq!(Emulator::ghostty_adapters(Some("1.2.0")), vec![Adapter::Kgp]);
+		assert_eq!(Emulator::ghostty_adapters(Some("1.3.0")), vec![Adapter::Kgp, Adapter::Sixel]);
+		assert_eq!(Emulator::ghostty_adapters(None), vec![Adapter::Kgp]);
+	}
+
+	#[test]
+	fn test_ghostty_platforms() {
+		for (version, linux, macos) in [
+			("1.1.3", vec![Adapter::Kgp], vec![Adapter::Kgp]),
+			("1.2.0", vec![Adapter::Kgp, Adapter::Sixel], vec![Adapter::Kgp]),
+			("1.2.3", vec![Adapter::Kgp, Adapter::Sixel], vec![Adapter::Kgp]),
+			("1.3.0", vec![Adapter::Kgp, Adapter::Sixel], vec![Adapter::Kgp, Adapter::Sixel]),
+		] {
+			assert_eq!(Emulator::ghostty_adapters_on(Some(version), false), linux, "{version}");
+			assert_eq!(Emulator::ghostty_adapters_on(Some(version), true), macos, "{version}");
+		}
+	}
+
+	async fn via_csi(replies: &[&str]) -> Emulator {
+		let (mut tx, rx) = tokio::io::duplex(256);
+		tokio::io::AsyncWriteExt::write_all(&mut tx, replies.concat().as_bytes()).await.unwrap();
+		drop(tx);
+
+		Emulator::via_csi_from(rx, |_| Ok(()), &CancellationToken::new()).await.unwrap().emulator
+	}
+
+	#[tokio::test]
+	async fn test_split_kgp_reply() {
+		// The rest of the KGP reply only arrives along with the reply to the last query
+		let e = via_csi(&["\x1b[?62;4c", "\x1b_Gi=31;\x1b[?62;4c", "OK\x1b\\\x1b[?62;4c"]).await;
+		assert_eq!(e, Emulator::Unknown(vec![Adapter::KgpOld, Adapter::Sixel]));
+
+		let e = via_csi(&["\x1b[?62c", "\x1b_Gi=31", ";OK\x1b\\\x1b[?62c", "\x1b[?62c"]).await;
+		assert_eq!(e, Emulator::Unknown(vec![Adapter::KgpOld]));
+	}
+
+	#[tokio::test]
+	async fn test_xtversion_retry() {
+		let xtversion = |replies: &[&str], tmux| {
+			let replies = replies.concat();
+			async move {
+				let (mut tx, rx) = tokio::io::duplex(256);
+				tokio::io::AsyncWriteExt::write_all(&mut tx, replies.as_bytes()).await.unwrap();
+				drop(tx);
+
+				let mut sent = 0;
+				let version = Emulator::xtversion_from(
+					rx,
+					|_| {
+						sent += 1;
+						Ok(())
+					},
+					&CancellationToken::new(),
+					tmux,
+				)
+				.await;
+				(version, sent)
+			}
+		};
+
+		// Dropped the first time under tmux, answered the second
+		let replies = ["\x1b[?62;4c", "\x1bP>|kitty(0.36.4)\x1b\\\x1b[?62;4c"];
+		assert_eq!(xtversion(&replies, true).await, (Some("kitty(0.36.4)".to_owned()), 2));
+		assert_eq!(xtversion(&replies, false).await, (None, 1));
+
+		// Never answered, given up after 3 attempts
+		let replies = ["\x1b[?62c", "\x1b[?62c", "\x1b[?62c", "\x1b[?62c"];
+		assert_eq!(xtversion(&replies, true).await, (None, 3));
+
+		// Answered right away
+		assert_eq!(xtversion(&["\x1bP>|WezTerm 20240203\x1b\\\x1b[?62c"], true).await.1, 1);
+	}
+
+	#[tokio::test]
+	async fn test_probe_timing() {
+		let (mut tx, rx) = tokio::io::duplex(256);
+		tokio::io::AsyncWriteExt::write_all(&mut tx, "\x1b[?62;4c".repeat(3).as_bytes()).await.unwrap();
+		drop(tx);
+
+		let send = |_: &str| Ok(std::thread::sleep(Duration::from_millis(2)));
+		let probe = Emulator::via_csi_from(rx, send, &CancellationToken::new()).await.unwrap();
+		assert_eq!(probe.emulator, Emulator::Unknown(vec![Adapter::Sixel]));
+
+		// Raw mode is only entered on a terminal
+		let ProbeTiming { raw_mode, write, read } = probe.timing;
+		assert_eq!(raw_mode, Duration::ZERO);
+		assert!(write >= Duration::from_millis(6), "{write:?}");
+		assert!(read > Duration::ZERO);
+		assert_eq!(probe.timing.total(), write + read);
+	}
+
+	#[test]
+	fn test_can_probe() {
+		assert!(Emulator::can_probe_with(true, true, None));
+		// Piped into a pager, or stderr redirected to a log file
+		assert!(!Emulator::can_probe_with(false, true, None));
+		assert!(!Emulator::can_probe_with(true, false, None));
+		assert!(!Emulator::can_probe_with(false, false, None));
+
+		assert!(Emulator::can_probe_with(false, true, Some("1")));
+		assert!(Emulator::can_probe_with(false, false, Some("true")));
+		assert!(!Emulator::can_probe_with(false, true, Some("0")));
+		assert!(!Emulator::can_probe_with(false, true, Some("")));
+		assert!(Emulator::can_probe_with(true, true, Some("0")));
+	}
+
+	#[test]
+	fn test_via_csi_no_tty() {
+		let mut buf = vec![];
+		assert_eq!(Emulator::via_csi_on(&mut buf, false).unwrap().emulator, Emulator::Unknown(vec![]));
+		assert!(buf.is_empty());
+	}
+
+	#[tokio::test]
+	async fn test_via_csi_partial() {
+		// XTVERSION answered
+		let e = via_csi(&["\x1bP>|kitty(0.36.4)\x1b\\\x1b[?62;c"]).await;
+		assert_eq!(e, Emulator::Kitty);
+
+		// No XTVERSION nor KGP reply, Sixel is still picked up
+		let e = via_csi(&["\x1b[?62;4c", "\x1b[?62;4c", "\x1b[?62;4c"]).await;
+		assert_eq!(e, Emulator::Unknown(vec![Adapter::Sixel]));
+
+		// KGP reply only
+		let e = via_csi(&["\x1b[?62c", "\x1b_Gi=31;OK\x1b\\\x1b[?62c", "\x1b[?62c"]).await;
+		assert_eq!(e, Emulator::Unknown(vec![Adapter::KgpOld]));
+
+		// The stream ends after the first reply
+		let e = via_csi(&["\x1b[?62;4;22c"]).await;
+		assert_eq!(e, Emulator::Unknown(vec![Adapter::Sixel]));
+	}
+
+	#[tokio::test]
+	async fn test_via_csi_sync_output() {
+		let probe = |replies: &'static str| async move {
+			let (mut tx, rx) = tokio::io::duplex(256);
+			tokio::io::AsyncWriteExt::write_all(&mut tx, replies.as_bytes()).await.unwrap();
+			drop(tx);
+			Emulator::via_csi_from(rx, |_| Ok(()), &CancellationToken::new()).await.unwrap()
+		};
+
+		// Answered along with XTVERSION, in the same exchange
+		let p = probe("\x1b[?2026;2$y\x1bP>|WezTerm 20240203\x1b\\\x1b[?65;4c").await;
+		assert_eq!((p.emulator, p.sync_output), (Emulator::WezTerm, true));
+
+		// Not recognized, nor answered at all
+		let p = probe("\x1b[?2026;0$y\x1bP>|kitty(0.36.4)\x1b\\\x1b[?62;c").await;
+		assert_eq!((p.emulator, p.sync_output), (Emulator::Kitty, false));
+		assert!(!probe("\x1b[?62;4c\x1b[?62;4c\x1b[?62;4c").await.sync_output);
+	}
+
+	#[tokio::test]
+	async fn test_via_csi_sixel_registers() {
+		let probe = |replies: &'static str| async move {
+			let (mut tx, rx) = tokio::io::duplex(256);
+			tokio::io::AsyncWriteExt::write_all(&mut tx, replies.as_bytes()).await.unwrap();
+			drop(tx);
+			Emulator::via_csi_from(rx, |_| Ok(()), &CancellationToken::new()).await.unwrap()
+		};
+
+		// Answered in the first exchange, without getting in the way of DA1
+		let p = probe("\x1b[?1;0;1024S\x1b[?62;4c\x1b[?62;4c\x1b[?62;4c").await;
+		assert_eq!(p.emulator, Emulator::Unknown(vec![Adapter::Sixel]));
+		assert_eq!(p.sixel_registers, Some(1024));
+
+		let p = probe("\x1b[?1;0;256S\x1bP>|foot(1.16.2)\x1b\\\x1b[?62;4;22c").await;
+		assert_eq!((p.emulator, p.sixel_registers), (Emulator::Foot, Some(256)));
+
+		// Failed, or not answered
+		let p = probe("\x1b[?1;3;0S\x1b[?62c\x1b[?62c\x1b[?62c").await;
+		assert_eq!((p.emulator, p.sixel_registers), (Emulator::Unknown(vec![]), None));
+		assert_eq!(probe("\x1b[?62c\x1b[?62c\x1b[?62c").await.sixel_registers, None);
+	}
+
+	#[tokio::test]
+	async fn test_via_csi_version() {
+		let probe = |replies: &'static str| async move {
+			let (mut tx, rx) = tokio::io::duplex(256);
+			tokio::io::AsyncWriteExt::write_all(&mut tx, replies.as_bytes()).await.unwrap();
+			drop(tx);
+			Emulator::via_csi_from(rx, |_| Ok(()), &CancellationToken::new()).await.unwrap()
+		};
+
+		// Kept for `version_string`, so it doesn't have to ask again
+		let p = probe("\x1bP>|WezTerm 20240203-110809-5046fc22\x1b\\\x1b[?62;4;22c").await;
+		assert_eq!(p.version.as_deref(), Some("WezTerm 20240203-110809-5046fc22"));
+
+		// Not answered
+		assert_eq!(probe("\x1b[?62c\x1b[?62c\x1b[?62c").await.version, None);
+	}
+
+	#[tokio::test]
+	async fn test_read_until_da1_c1() {
+		let read = |b: &'static [u8]| async move {
+			let (mut tx, rx) = tokio::io::duplex(64);
+			tokio::io::AsyncWriteExt::write_all(&mut tx, b).await.unwrap();
+
+			let deadline = Instant::now() + Duration::from_secs(1);
+			Emulator::read_until_da1_from(&mut { rx }, &CancellationToken::new(), deadline).await
+		};
+
+		assert_eq!(read(b"\x9b?62;4c").await, "\x1b[?62;4c");
+		assert_eq!(
+			read(b"\x90>|kitty(0.36.4)\x9c\x9b?62;c").await,
+			"\x1bP>|kitty(0.36.4)\x1b\\\x1b[?62;c"
+		);
+		// The KGP reply as an 8-bit APC, still acknowledging the query
+		let resp = read(b"\x9fGi=31;OK\x9c\x9b?62;c").await;
+		assert_eq!(resp, "\x1b_Gi=31;OK\x1b\\\x1b[?62;c");
+		assert_eq!(Emulator::classify_csi(&resp), Emulator::Unknown(vec![Adapter::KgpOld]));
+		// Mixed with 7-bit, and UTF-8 containing a 0x9b byte
+		assert_eq!(read(b"\x1bP>|\xc3\x9b\x1b\\\x9b?1;2c").await, "\x1bP>|\u{00db}\x1b\\\x1b[?1;2c");
+
+		let (mut tx, rx) = tokio::io::duplex(64);
+		tokio::io::AsyncWriteExt::write_all(&mut tx, b"\x90>|WezTerm 20240203\x9c\x9b?65;4c")
+			.await
+			.unwrap();
+		let e =
+			Emulator::via_csi_from(rx, |_| Ok(()), &CancellationToken::new()).await.unwrap().emulator;
+		assert_eq!(e, Emulator::WezTerm);
+	}
+
+	#[tokio::test]
+	async fn test_read_until_da1_cancel() {
+		let (mut tx, rx) = tokio::io::duplex(64);
+		tokio::io::AsyncWriteExt::write_all(&mut tx, b"\x1b_Gi=31;OK").await.unwrap();
+
+		let cancel = CancellationToken::new();
+		let canceller = cancel.clone();
+		tokio::spawn(async move {
+			tokio::time::sleep(Duration::from_millis(50)).await;
+			canceller.cancel();
+		});
+
+		let start = std::time::Instant::now();
+		let deadline = Instant::now() + Duration::from_secs(10);
+		let resp = Emulator::read_until_da1_from(&mut { rx }, &cancel, deadline).await;
+		assert!(start.elapsed() < Duration::from_secs(1));
+		assert_eq!(resp, "\x1b_Gi=31;OK");
+		drop(tx);
+	}
+
+	#[test]
+	fn test_cursor_resync() {
+		for e in Emulator::all() {
+			assert!(e.cursor_resync(true, None), "{e:?}");
+			if *e != Emulator::Konsole {
+				assert_eq!(e.cursor_resync(false, None), cfg!(windows), "{e:?}");
+			}
+		}
+
+		assert!(Emulator::Konsole.cursor_resync(false, Some("211202")));
+		assert!(Emulator::Konsole.cursor_resync(false, None));
+		assert_eq!(Emulator::Konsole.cursor_resync(false, Some("220400")), cfg!(windows));
+		assert_eq!(Emulator::Konsole.cursor_resync(false, Some("240802")), cfg!(windows));
+	}
+
+	#[test]
+	fn test_clear_seq() {
+		assert_eq!(Emulator::clear_seq(Adapter::Kgp, false).unwrap(), "\x1b_Gq=2,a=d,d=A\x1b\\");
+		assert_eq!(
+			Emulator::clear_seq(Adapter::KgpOld, true).unwrap(),
+			"\x1bPtmux;\x1b\x1b_Gq=2,a=d,d=A\x1b\x1b\\\x1b\\"
+		);
+		assert_eq!(Emulator::clear_seq(Adapter::Sixel, true).unwrap(), "\x1b[2J");
+		assert_eq!(Emulator::clear_seq(Adapter::Chafa, false), None);
+
+		assert_eq!(Emulator::Neovim.clear_images_seq(), None);
+		assert_eq!(Emulator::Unknown(vec![]).clear_images_seq(), None);
+	}
+
+	#[test]
+	fn test_write_reset() {
+		let reset = |e: Emulator, tmux| {
+			let mut buf = vec![];
+			e.write_reset(&mut buf, tmux).unwrap();
+			String::from_utf8(buf).unwrap()
+		};
+
+		assert_eq!(reset(Emulator::Kitty, false), "\x1b_Gq=2,a=d,d=A\x1b\\");
+		assert_eq!(reset(Emulator::Foot, false), "\x1b[2J");
+		assert_eq!(reset(Emulator::WezTerm, false), "\x1b[2J");
+		assert_eq!(reset(Emulator::Konsole, false), "\x1b_Gq=2,a=d,d=A\x1b\\");
+		assert_eq!(
+			reset(Emulator::Unknown(vec![Adapter::KgpOld, Adapter::Sixel]), true),
+			"\x1bPtmux;\x1b\x1b_Gq=2,a=d,d=A\x1b\x1b\\\x1b\\\x1b[2J"
+		);
+		assert_eq!(reset(Emulator::Neovim, false), "");
+		assert_eq!(reset(Emulator::Apple, true), "");
+	}
+
+	#[test]
+	fn test_sixel_opts() {
+		assert_eq!(Emulator::Foot.sixel_opts(), SixelOpts { palette: 256, dither: false });
+		assert_eq!(Emulator::WezTerm.sixel_opts(), SixelOpts { palette: 256, dither: false });
+		assert_eq!(Emulator::Unknown(vec![Adapter::Sixel]).sixel_opts(), SixelOpts {
+			palette: 16,
+			dither:  true,
+		});
+
+		let opts = Emulator::Foot.sixel_opts().with_overrides(Some(64), None);
+		assert_eq!(opts, SixelOpts { palette: 64, dither: false });
+		let opts = Emulator::Unknown(vec![]).sixel_opts().with_overrides(None, Some(false));
+		assert_eq!(opts, SixelOpts { palette: 16, dither: false });
+	}
+
+	#[test]
+	fn test_quiet_probe() {
+		let mut buf = vec![];
+		Emulator::quiet_probe(&mut buf, "\x1b[>q\x1b[c").unwrap();
+		assert_eq!(
+			String::from_utf8(buf).unwrap(),
+			"\x1b[?2026h\x1b[8m\x1b7\x1b[>q\x1b[c\x1b8\x1b[28m\x1b[?2026l"
+		);
+	}
+
+	#[test]
+	fn test_parse_sync_output() {
+		assert!(Emulator::parse_sync_output("\x1b[?2026;2$y\x1b[?62;4c"));
+		assert!(Emulator::parse_sync_output("\x1b[?2026;1$y\x1b[?62c"));
+		assert!(!Emulator::parse_sync_output("\x1b[?2026;0$y\x1b[?62c"));
+		assert!(!Emulator::parse_sync_output("\x1b[?2026;4$y\x1b[?62c"));
+		assert!(!Emulator::parse_sync_output("\x1b[?62;4c"));
+	}
+
+	#[test]
+	fn test_dark_background() {
+		let dark = |s: &str| Emulator::dark_background(None, Some(s));
+		assert!(dark("15;0"));
+		assert!(!dark("0;15"));
+		assert!(!dark("0;7"));
+		assert!(dark("7;8"));
+		assert!(!dark("0;default;11"));
+		assert!(dark("15;default;4"));
+		assert!(dark("default;default"));
+		assert!(dark(""));
+		assert!(Emulator::dark_background(None, None));
+
+		// The reported color takes precedence
+		assert!(!Emulator::dark_background(Some((250, 250, 240)), Some("15;0")));
+		assert!(Emulator::dark_background(Some((30, 30, 46)), Some("0;15")));
+	}
+
+	#[test]
+	fn test_probe_guarded() {
+		let probe = |keyboard, fail| {
+			let mut buf = Vec::new();
+			let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+				Emulator::probe_guarded(&mut buf, false, keyboard, |w| {
+					w.write_all(b"\x1b[8m\x1b7\x1b[c").unwrap();
+					if fail {
+						panic!("probe failed");
+					}
+				})
+			}));
+			assert_eq!(result.is_err(), fail);
+			String::from_utf8(buf).unwrap()
+		};
+
+		assert_eq!(probe(false, false), "\x1b[8m\x1b7\x1b[c");
+		assert_eq!(probe(true, false), "\x1b[>0u\x1b[8m\x1b7\x1b[c\x1b[<u");
+
+		// Restored after a panic, before the keyboard flags are
+		assert_eq!(probe(false, true), "\x1b[8m\x1b7\x1b[c\x1b8\x1b[28m\x1b[?2026l");
+		assert_eq!(probe(true, true), "\x1b[>0u\x1b[8m\x1b7\x1b[c\x1b8\x1b[28m\x1b[?2026l\x1b[<u");
+	}
+
+	#[test]
+	fn test_parse_keyboard_flags() {
+		let p = Emulator::parse_keyboard_flags;
+		assert_eq!(p("\x1b[?0u\x1b[?62;4c"), Some(0));
+		assert_eq!(p("\x1b[?15u\x1b[?62c"), Some(15));
+		assert_eq!(p("\x1bP>|kitty(0.36.4)\x1b\\\x1b[?1u\x1b[?62c"), Some(1));
+		assert_eq!(p("\x1b[?62c\x1b[?31u"), Some(31));
+
+		// Not supported, only DA1 comes back
+		assert_eq!(p("\x1b[?62;4c"), None);
+		assert_eq!(p("\x1b[?62;4;22c"), None);
+		assert_eq!(p(""), None);
+		assert_eq!(p("\x1b[?u"), None);
+	}
+
+	#[test]
+	fn test_parse_bg_color() {
+		assert_eq!(
+			Emulator::parse_bg_color("\x1b]11;rgb:1e1e/1e1e/2e2e\x1b\\\x1b[?62c"),
+			Some((30, 30, 46))
+		);
+		assert_eq!(Emulator::parse_bg_color("\x1b]11;rgb:ff/ff/ff\x07"), Some((255, 255, 255)));
+		assert_eq!(Emulator::parse_bg_color("\x1b]11;rgb:f/0/8\x07"), Some((255, 0, 136)));
+		assert_eq!(Emulator::parse_bg_color("\x1b]11;rgb:ffff/ffff\x07"), None);
+		assert_eq!(Emulator::parse_bg_color("\x1b]11;rgb:fffff/0/0\x07"), None);
+		assert_eq!(Emulator::parse_bg_color("\x1b[?62c"), None);
+	}
+
+	#[test]
+	fn test_pixel_size() {
+		assert_eq!(Emulator::parse_pixel_size("\x1b[4;1080;1920t\x1b[?62c"), Some((1920, 1080)));
+		assert_eq!(Emulator::parse_pixel_size("\x1b[4;0;0t\x1b[?62c"), None);
+		assert_eq!(Emulator::parse_pixel_size("\x1b[4;1080t\x1b[?62c"), None);
+		assert_eq!(Emulator::parse_pixel_size("\x1b[?62c"), None);
+
+		assert_eq!(
+			Emulator::pixel_size_via(false, || Some("\x1b[4;600;800t".to_owned())),
+			Some((800, 600))
+		);
+		assert_eq!(Emulator::pixel_size_via(true, || Some("\x1b[4;600;800t".to_owned())), None);
+	}
+
+	#[test]
+	fn test_text_area_cells() {
+		assert_eq!(Emulator::parse_text_area_cells("\x1b[8;24;80t\x1b[?62c"), Some((80, 24)));
+		assert_eq!(Emulator::parse_text_area_cells("\x1b[8;50;200t"), Some((200, 50)));
+		assert_eq!(Emulator::parse_text_area_cells("\x1b[8;0;80t\x1b[?62c"), None);
+		assert_eq!(Emulator::parse_text_area_cells("\x1b[8;24t\x1b[?62c"), None);
+		assert_eq!(Emulator::parse_text_area_cells("\x1b[8;24;99999t"), None);
+		// A pixel size reply isn't mistaken for it
+		assert_eq!(Emulator::parse_text_area_cells("\x1b[4;1080;1920t\x1b[?62c"), None);
+		assert_eq!(Emulator::parse_text_area_cells("\x1b[?62c"), None);
+
+		assert_eq!(
+			Emulator::text_area_cells_via(false, || Some("\x1b[8;40;120t".to_owned())),
+			Some((120, 40))
+		);
+		assert_eq!(Emulator::text_area_cells_via(true, || Some("\x1b[8;40;120t".to_owned())), None);
+	}
+
+	#[test]
+	fn test_neovim_geometry() {
+		assert!(Emulator::Neovim.adapters().is_empty());
+		assert!(!Emulator::Neovim.queries_geometry_with(None));
+		assert!(!Emulator::Neovim.queries_geometry_with(Some("0")));
+		assert!(Emulator::Neovim.queries_geometry_with(Some("1")));
+		assert!(Emulator::Kitty.queries_geometry_with(None));
+
+		assert_eq!(Emulator::cell_size_from((1920, 1080), (240, 60)), Some((8, 18)));
+		assert_eq!(Emulator::cell_size_from((1925, 1090), (240, 60)), Some((8, 18)));
+		assert_eq!(Emulator::cell_size_from((100, 100), (240, 60)), None);
+	}
+
+	#[test]
+	fn test_iip_preserve_aspect() {
+		assert!(!Emulator::VSCode.iip_preserve_aspect());
+		assert!(Emulator::Tabby.iip_preserve_aspect());
+		assert!(Emulator::Iterm2.iip_preserve_aspect());
+		assert!(Emulator::Unknown(vec![Adapter::Iip]).iip_preserve_aspect());
+	}
+
+	#[test]
+	fn test_needs_cursor_show_after_image() {
+		for e in Emulator::all() {
+			assert_eq!(e.needs_cursor_show_after_image(), *e == Emulator::Konsole, "{}", e.name());
+		}
+		assert!(!Emulator::Unknown(vec![Adapter::Sixel]).needs_cursor_show_after_image());
+	}
+
+	#[test]
+	fn test_detect_hook() {
+		struct Fake;
+		impl crate::DetectHook for Fake {
+			fn detect(&self) -> Option<Emulator> { Some(Emulator::Unknown(vec![Adapter::Kgp])) }
+		}
+
+		// Against a registry of its own, as the global one can't be unregistered from
+		let hooks: Vec<Box<dyn crate::DetectHook>> = vec![Box::new(|| None), Box::new(Fake)];
+		assert_eq!(crate::detect_via(&hooks), Some(Emulator::Unknown(vec![Adapter::Kgp])));
+		assert_eq!(crate::detect_via(&hooks[..1]), None);
+	}
+
+	#[test]
+	fn test_unicode_placeholders() {
+		assert!(Emulator::Kitty.unicode_placeholders(Some("kitty(0.36.4)")));
+		assert!(!Emulator::Kitty.unicode_placeholders(Some("kitty(0.26.5)")));
+		assert!(!Emulator::Kitty.unicode_placeholders(None));
+		assert!(Emulator::Ghostty.unicode_placeholders(Some("ghostty 1.1.3")));
+		assert!(!Emulator::WezTerm.unicode_placeholders(Some("WezTerm 20240203-110809-5046fc22")));
+	}
+
+	#[test]
+	fn test_all() {
+		let all = Emulator::all();
+		for e in all {
+			assert_eq!(all.iter().filter(|&x| x == e).count(), 1, "{e:?}");
+			assert!(!matches!(e, Emulator::Unknown(_)));
+		}
+
+		// Exhaustive on purpose, so that a new variant can't be left out of `all()`
+		let index = |e: &Emulator| match e {
+			Emulator::Unknown(_) => unreachable!(),
+			Emulator::Kitty => 0,
+			Emulator::Konsole => 1,
+			Emulator::Iterm2 => 2,
+			Emulator::WezTerm => 3,
+			Emulator::Foot => 4,
+			Emulator::Ghostty => 5,
+			Emulator::Microsoft => 6,
+			Emulator::Rio => 7,
+			Emulator::BlackBox => 8,
+			Emulator::VSCode => 9,
+			Emulator::Tabby => 10,
+			Emulator::Hyper => 11,
+			Emulator::Mintty => 12,
+			Emulator::Neovim => 13,
+			Emulator::Apple => 14,
+			Emulator::Urxvt => 15,
+			Emulator::Contour => 16,
+			Emulator::Wayst => 17,
+			Emulator::Bobcat => 18,
+			Emulator::Eat => 19,
+		};
+		let mut indices: Vec<_> = all.iter().map(index).collect();
+		indices.sort_unstable();
+		assert_eq!(indices, (0..20).collect::<Vec<_>>());
+
+		for ((name, adapters), e) in Emulator::table().into_iter().zip(all) {
+			assert_eq!(name, e.name());
+			assert_eq!(adapters, e.clone().adapters());
 		}
-		String::from_utf8_lossy(&buf).into_owned()
 	}
 }
@@ -0,0 +1,31 @@
+use std::sync::RwLock;
+
+use crate::Emulator;
+
+static HOOKS: RwLock<Vec<Box<dyn DetectHook>>> = RwLock::new(Vec::new());
+
+/// Lets embedders decide the emulator themselves, e.g. a GUI frontend that
+/// knows it supports KGP, instead of relying on the environment and CSI probe.
+pub trait DetectHook: Send + Sync {
+	fn detect(&self) -> Option<Emulator>;
+}
+
+impl<F> DetectHook for F
+where
+	F: Fn() -> Option<Emulator> + Send + Sync,
+{
+	fn detect(&self) -> Option<Emulator> { self() }
+}
+
+/// Registers a hook consulted by [`Emulator::detect`], in registration order,
+/// before any built-in detection. Must be called before [`crate::init`].
+pub fn register_detect_hook(hook: impl DetectHook + 'static) {
+	HOOKS.write().unwrap().push(Box::new(hook));
+}
+
+pub(super) fn detect_via_hooks() -> Option<Emulator> { detect_via(&HOOKS.read().unwrap()) }
+
+// Asks each of `hooks` in order, and takes the first answer
+pub(super) fn detect_via(hooks: &[Box<dyn DetectHook>]) -> Option<Emulator> {
+	hooks.iter().find_map(|h| h.detect())
+}
@@ -5,18 +5,20 @@ use base64::{Engine, engine::{Config, general_purpose::STANDARD}};
 use crossterm::{cursor::MoveTo, queue};
 use image::{DynamicImage, ExtendedColorType, ImageEncoder, codecs::{jpeg::JpegEncoder, png::PngEncoder}};
 use ratatui::layout::Rect;
-use yazi_config::PREVIEW;
 
 use super::image::Image;
-use crate::{CLOSE, Emulator, START, adapter::Adapter};
+use crate::{Emulator, ImagePlacer, Mux, adapter::Adapter};
 
-pub(super) struct Iip;
+pub(super) struct Iip {
+	pub(super) preserve_aspect: bool,
+	pub(super) quality:         u8,
+}
 
 impl Iip {
 	pub(super) async fn image_show(path: &Path, max: Rect) -> Result<Rect> {
 		let img = Image::downscale(path, max).await?;
 		let area = Image::pixel_area((img.width(), img.height()), max);
-		let b = Self::encode(img).await?;
+		let b = Adapter::Iip.place(img, area).await?;
 
 		Adapter::Iip.image_hide()?;
 		Adapter::shown_store(area);
@@ -37,34 +39,64 @@ impl Iip {
 		})
 	}
 
-	async fn encode(img: DynamicImage) -> Result<Vec<u8>> {
-		tokio::task::spawn_blocking(move || {
-			let (w, h) = (img.width(), img.height());
-
-			let mut b = vec![];
-			if img.color().has_alpha() {
-				PngEncoder::new(&mut b).write_image(&img.into_rgba8(), w, h, ExtendedColorType::Rgba8)?;
-			} else {
-				JpegEncoder::new_with_quality(&mut b, PREVIEW.image_quality).encode_image(&img)?;
-			};
-
-			let mut buf = String::with_capacity(
-				200 + base64::encoded_len(b.len(), STANDARD.config().encode_padding()).unwrap_or(0),
-			);
-
-			write!(
-				buf,
-				"{}]1337;File=inline=1;size={};width={}px;height={}px;doNotMoveCursor=1:",
-				START,
-				b.len(),
-				w,
-				h,
-			)?;
-			STANDARD.encode_string(b, &mut buf);
-			write!(buf, "\x07{}", CLOSE)?;
-
-			Ok(buf.into_bytes())
-		})
-		.await?
+	fn encode(&self, img: &DynamicImage, mux: bool) -> Result<Vec<u8>> {
+		let (start, _, close) = Mux::parts(mux);
+		let (w, h) = (img.width(), img.height());
+
+		let mut b = vec![];
+		if img.color().has_alpha() {
+			PngEncoder::new(&mut b).write_image(&img.to_rgba8(), w, h, ExtendedColorType::Rgba8)?;
+		} else {
+			JpegEncoder::new_with_quality(&mut b, self.quality).encode_image(img)?;
+		};
+
+		let mut buf = String::with_capacity(
+			200 + base64::encoded_len(b.len(), STANDARD.config().encode_padding()).unwrap_or(0),
+		);
+
+		write!(
+			buf,
+			"{}]1337;File=inline=1;size={};width={}px;height={}px;{}doNotMoveCursor=1:",
+			start,
+			b.len(),
+			w,
+			h,
+			if self.preserve_aspect { "preserveAspectRatio=1;" } else { "" },
+		)?;
+		STANDARD.encode_string(b, &mut buf);
+		write!(buf, "\x07{}", close)?;
+
+		Ok(buf.into_bytes())
+	}
+}
+
+impl ImagePlacer for Iip {
+	// Drawn at the cursor with its size in pixels, so `rect` isn't needed
+	fn place(&self, img: &DynamicImage, _: Rect, mux: bool) -> Vec<u8> {
+		self.encode(img, mux).unwrap_or_default()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use image::{RgbImage, RgbaImage};
+
+	use super::*;
+
+	#[test]
+	fn test_place() {
+		let iip = Iip { preserve_aspect: true, quality: 75 };
+		let img = DynamicImage::ImageRgba8(RgbaImage::new(3, 2));
+		let b = String::from_utf8(iip.place(&img, Rect::default(), false)).unwrap();
+		assert!(b.starts_with("\x1b]1337;File=inline=1;size="));
+		assert!(b.contains(";width=3px;height=2px;preserveAspectRatio=1;doNotMoveCursor=1:iVBOR"));
+		assert!(b.ends_with('\x07'));
+
+		let iip = Iip { preserve_aspect: false, quality: 75 };
+		let img = DynamicImage::ImageRgb8(RgbImage::new(3, 2));
+		let b = String::from_utf8(iip.place(&img, Rect::default(), true)).unwrap();
+		assert!(b.starts_with("\x1bPtmux;\x1b\x1b]1337;File=inline=1;size="));
+		assert!(b.contains(";width=3px;height=2px;doNotMoveCursor=1:/9j/"));
+		assert!(b.ends_with("\x07\x1b\\"));
 	}
 }
@@ -8,7 +8,7 @@ use image::DynamicImage;
 use ratatui::layout::Rect;
 
 use super::image::Image;
-use crate::{CLOSE, ESCAPE, Emulator, START, adapter::Adapter};
+use crate::{CLOSE, ESCAPE, Emulator, ImagePlacer, Mux, START, adapter::Adapter};
 
 static DIACRITICS: [char; 297] = [
 	'\u{0305}',
@@ -316,15 +316,12 @@ impl Kgp {
 	pub(super) async fn image_show(path: &Path, max: Rect) -> Result<Rect> {
 		let img = Image::downscale(path, max).await?;
 		let area = Image::pixel_area((img.width(), img.height()), max);
-
-		let b1 = Self::encode(img).await?;
-		let b2 = Self::place(&area)?;
+		let b = Adapter::Kgp.place(img, area).await?;
 
 		Adapter::Kgp.image_hide()?;
 		Adapter::shown_store(area);
 		Emulator::move_lock((area.x, area.y), |stderr| {
-			stderr.write_all(&b1)?;
-			stderr.write_all(&b2)?;
+			stderr.write_all(&b)?;
 			Ok(area)
 		})
 	}
@@ -342,8 +339,9 @@ impl Kgp {
 		})
 	}
 
-	async fn encode(img: DynamicImage) -> Result<Vec<u8>> {
-		fn output(raw: &[u8], format: u8, size: (u32, u32)) -> Result<Vec<u8>> {
+	fn encode(img: &DynamicImage, mux: bool) -> Result<Vec<u8>> {
+		fn output(raw: &[u8], format: u8, size: (u32, u32), mux: bool) -> Result<Vec<u8>> {
+			let (start, escape, close) = Mux::parts(mux);
 			let b64 = general_purpose::STANDARD.encode(raw).into_bytes();
 
 			let mut it = b64.chunks(4096).peekable();
@@ -352,14 +350,14 @@ impl Kgp {
 				write!(
 					buf,
 					"{}_Gq=2,a=T,i=1,C=1,U=1,f={},s={},v={},m={};{}{}\\{}",
-					START,
+					start,
 					format,
 					size.0,
 					size.1,
 					it.peek().is_some() as u8,
 					unsafe { str::from_utf8_unchecked(first) },
-					ESCAPE,
-					CLOSE
+					escape,
+					close
 				)?;
 			}
 
@@ -367,28 +365,27 @@ impl Kgp {
 				write!(
 					buf,
 					"{}_Gm={};{}{}\\{}",
-					START,
+					start,
 					it.peek().is_some() as u8,
 					unsafe { str::from_utf8_unchecked(chunk) },
-					ESCAPE,
-					CLOSE
+					escape,
+					close
 				)?;
 			}
 
-			write!(buf, "{}", CLOSE)?;
+			write!(buf, "{}", close)?;
 			Ok(buf)
 		}
 
 		let size = (img.width(), img.height());
-		tokio::task::spawn_blocking(move || match img {
-			DynamicImage::ImageRgb8(v) => output(v.as_raw(), 24, size),
-			DynamicImage::ImageRgba8(v) => output(v.as_raw(), 32, size),
-			v => output(v.into_rgb8().as_raw(), 24, size),
-		})
-		.await?
+		match img {
+			DynamicImage::ImageRgb8(v) => output(v.as_raw(), 24, size, mux),
+			DynamicImage::ImageRgba8(v) => output(v.as_raw(), 32, size, mux),
+			v => output(v.to_rgb8().as_raw(), 24, size, mux),
+		}
 	}
 
-	fn place(area: &Rect) -> Result<Vec<u8>> {
+	fn cells(area: Rect) -> Result<Vec<u8>> {
 		let mut buf = Vec::with_capacity(area.width as usize * area.height as usize * 3 + 50);
 		for y in 0..area.height {
 			write!(buf, "\x1b[{};{}H\x1b[38;5;1m", area.y + y + 1, area.x + 1)?;
@@ -402,3 +399,35 @@ impl Kgp {
 		Ok(buf)
 	}
 }
+
+impl ImagePlacer for Kgp {
+	// Transmits the image, then fills `rect` with placeholder cells referring to it
+
+	fn place(&self, img: &DynamicImage, rect: Rect, mux: bool) -> Vec<u8> {
+		let (Ok(mut b), Ok(cells)) = (Self::encode(img, mux), Self::cells(rect)) else {
+			return vec![];
+		};
+		b.extend(cells);
+		b
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use image::RgbImage;
+
+	use super::*;
+
+	#[test]
+	fn test_place() {
+		let img = DynamicImage::ImageRgb8(RgbImage::new(2, 3));
+		let b = String::from_utf8(Kgp.place(&img, Rect::new(4, 5, 2, 1), false)).unwrap();
+		assert!(b.starts_with("\x1b_Gq=2,a=T,i=1,C=1,U=1,f=24,s=2,v=3,m=0;"));
+		assert!(b.contains("\x1b\\\x1b[6;5H\x1b[38;5;1m\u{10EEEE}"));
+		assert!(b.ends_with("\x1b[0m"));
+
+		let b = String::from_utf8(Kgp.place(&img, Rect::new(0, 0, 1, 1), true)).unwrap();
+		assert!(b.starts_with("\x1bPtmux;\x1b\x1b_Gq=2,a=T,i=1,C=1,U=1,f=24,s=2,v=3,m=0;"));
+		assert!(b.contains("\x1b\x1b\\\x1b\\"));
+	}
+}
@@ -7,7 +7,7 @@ use image::DynamicImage;
 use ratatui::layout::Rect;
 
 use super::image::Image;
-use crate::{CLOSE, ESCAPE, Emulator, START, adapter::Adapter};
+use crate::{CLOSE, ESCAPE, Emulator, ImagePlacer, Mux, START, adapter::Adapter};
 
 pub(super) struct KgpOld;
 
@@ -15,7 +15,7 @@ impl KgpOld {
 	pub(super) async fn image_show(path: &Path, max: Rect) -> Result<Rect> {
 		let img = Image::downscale(path, max).await?;
 		let area = Image::pixel_area((img.width(), img.height()), max);
-		let b = Self::encode(img).await?;
+		let b = Adapter::KgpOld.place(img, area).await?;
 
 		Adapter::KgpOld.image_hide()?;
 		Adapter::shown_store(area);
@@ -33,8 +33,9 @@ impl KgpOld {
 		Ok(())
 	}
 
-	async fn encode(img: DynamicImage) -> Result<Vec<u8>> {
-		fn output(raw: &[u8], format: u8, size: (u32, u32)) -> Result<Vec<u8>> {
+	fn encode(img: &DynamicImage, mux: bool) -> Result<Vec<u8>> {
+		fn output(raw: &[u8], format: u8, size: (u32, u32), mux: bool) -> Result<Vec<u8>> {
+			let (start, escape, close) = Mux::parts(mux);
 			let b64 = general_purpose::STANDARD.encode(raw).into_bytes();
 
 			let mut it = b64.chunks(4096).peekable();
@@ -43,14 +44,14 @@ impl KgpOld {
 				write!(
 					buf,
 					"{}_Gq=2,a=T,z=-1,C=1,f={},s={},v={},m={};{}{}\\{}",
-					START,
+					start,
 					format,
 					size.0,
 					size.1,
 					it.peek().is_some() as u8,
 					unsafe { str::from_utf8_unchecked(first) },
-					ESCAPE,
-					CLOSE
+					escape,
+					close
 				)?;
 			}
 
@@ -58,24 +59,49 @@ impl KgpOld {
 				write!(
 					buf,
 					"{}_Gm={};{}{}\\{}",
-					START,
+					start,
 					it.peek().is_some() as u8,
 					unsafe { str::from_utf8_unchecked(chunk) },
-					ESCAPE,
-					CLOSE
+					escape,
+					close
 				)?;
 			}
 
-			write!(buf, "{}", CLOSE)?;
+			write!(buf, "{}", close)?;
 			Ok(buf)
 		}
 
 		let size = (img.width(), img.height());
-		tokio::task::spawn_blocking(move || match img {
-			DynamicImage::ImageRgb8(v) => output(v.as_raw(), 24, size),
-			DynamicImage::ImageRgba8(v) => output(v.as_raw(), 32, size),
-			v => output(v.into_rgb8().as_raw(), 24, size),
-		})
-		.await?
+		match img {
+			DynamicImage::ImageRgb8(v) => output(v.as_raw(), 24, size, mux),
+			DynamicImage::ImageRgba8(v) => output(v.as_raw(), 32, size, mux),
+			v => output(v.to_rgb8().as_raw(), 24, size, mux),
+		}
+	}
+}
+
+impl ImagePlacer for KgpOld {
+	// Placed directly at the cursor, so `rect` isn't needed
+	fn place(&self, img: &DynamicImage, _: Rect, mux: bool) -> Vec<u8> {
+		Self::encode(img, mux).unwrap_or_default()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use image::RgbaImage;
+
+	use super::*;
+
+	#[test]
+	fn test_place() {
+		let img = DynamicImage::ImageRgba8(RgbaImage::new(3, 2));
+		let b = String::from_utf8(KgpOld.place(&img, Rect::default(), false)).unwrap();
+		assert!(b.starts_with("\x1b_Gq=2,a=T,z=-1,C=1,f=32,s=3,v=2,m=0;"));
+		assert!(b.ends_with("\x1b\\"));
+
+		let b = String::from_utf8(KgpOld.place(&img, Rect::default(), true)).unwrap();
+		assert!(b.starts_with("\x1bPtmux;\x1b\x1b_Gq=2,a=T,z=-1,C=1,f=32,s=3,v=2,m=0;"));
+		assert!(b.ends_with("\x1b\x1b\\\x1b\\\x1b\\"));
 	}
 }
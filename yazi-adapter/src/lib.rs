@@ -1,11 +1,13 @@
 #![allow(clippy::unit_arg)]
 
 yazi_macro::mod_flat!(
-	adapter chafa dimension emulator iip image kgp kgp_old mux sixel ueberzug
+	adapter chafa dimension emulator hook iip image kgp kgp_old mux placer probe report sixel ueberzug
 );
 
 use yazi_shared::{RoCell, env_exists, in_wsl};
 pub static ADAPTOR: RoCell<Adapter> = RoCell::new();
+pub static EMULATOR: RoCell<Emulator> = RoCell::new();
+pub static SIXEL_OPTS: RoCell<SixelOpts> = RoCell::new();
 
 // Tmux support
 pub static TMUX: RoCell<bool> = RoCell::new();
@@ -22,9 +24,10 @@ static SHOWN: RoCell<arc_swap::ArcSwapOption<ratatui::layout::Rect>> = RoCell::n
 pub fn init() {
 	// Tmux support
 	TMUX.init(env_exists("TMUX_PANE") && env_exists("TMUX"));
-	ESCAPE.init(if *TMUX { "\x1b\x1b" } else { "\x1b" });
-	START.init(if *TMUX { "\x1bPtmux;\x1b\x1b" } else { "\x1b" });
-	CLOSE.init(if *TMUX { "\x1b\\" } else { "" });
+	let (start, escape, close) = Mux::parts(*TMUX);
+	ESCAPE.init(escape);
+	START.init(start);
+	CLOSE.init(close);
 
 	if *TMUX {
 		_ = std::process::Command::new("tmux")
@@ -41,6 +44,18 @@ pub fn init() {
 	// Image state
 	SHOWN.with(<_>::default);
 
-	ADAPTOR.init(Adapter::matches());
+	EMULATOR.init(Emulator::detect());
+	ADAPTOR.init(Adapter::matches(EMULATOR.clone()));
+	SIXEL_OPTS.init({
+		let opts = EMULATOR
+			.sixel_opts()
+			.with_overrides(yazi_config::PREVIEW.sixel_palette, yazi_config::PREVIEW.sixel_dither);
+		// Only probe the registers if Sixel is going to be used at all
+		if *ADAPTOR == Adapter::Sixel {
+			SixelOpts { palette: opts.palette.min(EMULATOR.sixel_palette_size()), ..opts }
+		} else {
+			opts
+		}
+	});
 	ADAPTOR.start();
 }
@@ -1,21 +1,30 @@
-use crate::{CLOSE, ESCAPE, START, TMUX};
+use std::{borrow::Cow, process::{Command, Stdio}, thread, time::{Duration, Instant}};
+
+use crate::TMUX;
 
 pub struct Mux;
 
 impl Mux {
-	pub fn csi(s: &str) -> std::borrow::Cow<str> {
-		if *TMUX {
-			std::borrow::Cow::Owned(format!(
-				"{}{}{}",
-				*START,
-				s.trim_start_matches('\x1b').replace('\x1b', *ESCAPE),
-				*CLOSE
+	#[inline]
+	pub fn csi(s: &str) -> Cow<str> { Self::csi_with(s, *TMUX) }
+
+	// Same as `csi()`, with the tmux state passed in rather than read from `TMUX`
+	pub(super) fn csi_with(s: &str, tmux: bool) -> Cow<'_, str> {
+		if tmux {
+			Cow::Owned(format!(
+				"\x1bPtmux;\x1b\x1b{}\x1b\\",
+				s.trim_start_matches('\x1b').replace('\x1b', "\x1b\x1b"),
 			))
 		} else {
-			std::borrow::Cow::Borrowed(s)
+			Cow::Borrowed(s)
 		}
 	}
 
+	// The (start, escape, close) parts wrapping a sequence for tmux passthrough
+	pub(super) fn parts(tmux: bool) -> (&'static str, &'static str, &'static str) {
+		if tmux { ("\x1bPtmux;\x1b\x1b", "\x1b\x1b", "\x1b\\") } else { ("\x1b", "\x1b", "") }
+	}
+
 	pub fn tmux_sixel_flag() -> &'static str {
 		let stdout = std::process::Command::new("tmux")
 			.args(["-LwU0dju1is5", "-f/dev/null", "start", ";", "display", "-p", "#{sixel_support}"])
@@ -31,6 +40,51 @@ impl Mux {
 		}
 	}
 
+	/// Whether tmux's `allow-passthrough` is on for the current pane,
+	/// `None` if not running under tmux or it couldn't be determined.
+	pub fn passthrough_enabled() -> Option<bool> {
+		if !*TMUX {
+			return None;
+		}
+
+		// Yazi sets the pane option on startup, so it takes precedence over the global
+		Self::tmux_output(&["show", "-pv", "allow-passthrough"])
+			.and_then(|s| Self::parse_passthrough(&s))
+			.or_else(|| {
+				Self::parse_passthrough(&Self::tmux_output(&["show", "-gv", "allow-passthrough"])?)
+			})
+	}
+
+	fn parse_passthrough(stdout: &str) -> Option<bool> {
+		match stdout.trim() {
+			"on" | "all" => Some(true),
+			"off" => Some(false),
+			_ => None,
+		}
+	}
+
+	fn tmux_output(args: &[&str]) -> Option<String> {
+		let mut child = Command::new("tmux")
+			.args(args)
+			.stdin(Stdio::null())
+			.stdout(Stdio::piped())
+			.stderr(Stdio::null())
+			.spawn()
+			.ok()?;
+
+		let start = Instant::now();
+		while child.try_wait().ok()?.is_none() {
+			if start.elapsed() > Duration::from_millis(500) {
+				child.kill().ok();
+				return None;
+			}
+			thread::sleep(Duration::from_millis(10));
+		}
+
+		let output = child.wait_with_output().ok()?;
+		output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+	}
+
 	pub(super) fn term_program() -> (Option<String>, Option<String>) {
 		let (mut term, mut program) = (None, None);
 		if !*TMUX {
@@ -54,3 +108,17 @@ impl Mux {
 		(term, program)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_passthrough() {
+		assert_eq!(Mux::parse_passthrough("on\n"), Some(true));
+		assert_eq!(Mux::parse_passthrough("all\n"), Some(true));
+		assert_eq!(Mux::parse_passthrough("off\n"), Some(false));
+		assert_eq!(Mux::parse_passthrough(""), None);
+		assert_eq!(Mux::parse_passthrough("invalid option: allow-passthrough\n"), None);
+	}
+}
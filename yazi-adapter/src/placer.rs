@@ -0,0 +1,61 @@
+use anyhow::{Result, bail};
+use crossterm::{cursor::Show, queue};
+use image::DynamicImage;
+use ratatui::layout::Rect;
+use yazi_config::PREVIEW;
+
+use crate::{Adapter, EMULATOR, Emulator, Iip, Kgp, KgpOld, SIXEL_OPTS, Sixel, TMUX};
+
+/// Builds the escape sequences that draw an image with a given protocol.
+pub trait ImagePlacer: Send + Sync {
+	/// Encodes `img` to be drawn at `rect`, wrapped for tmux passthrough if
+	/// `mux`. Empty if the image can't be encoded.
+	fn place(&self, img: &DynamicImage, rect: Rect, mux: bool) -> Vec<u8>;
+}
+
+// Überzug++ and Chafa draw images themselves, there's nothing to emit
+struct NonePlacer;
+
+impl ImagePlacer for NonePlacer {
+	fn place(&self, _: &DynamicImage, _: Rect, _: bool) -> Vec<u8> { vec![] }
+}
+
+impl Emulator {
+	/// The placer for the adapter this emulator would be matched with.
+	pub fn placer(&self) -> Box<dyn ImagePlacer> { Adapter::matches(self.clone()).placer(self) }
+}
+
+impl Adapter {
+	pub(super) fn placer(self, emulator: &Emulator) -> Box<dyn ImagePlacer> {
+		match self {
+			Self::Kgp => Box::new(Kgp),
+			Self::KgpOld => Box::new(KgpOld),
+			Self::Iip => Box::new(Iip {
+				preserve_aspect: emulator.iip_preserve_aspect(),
+				quality:         PREVIEW.image_quality,
+			}),
+			Self::Sixel => Box::new(Sixel {
+				opts:     if emulator == &*EMULATOR {
+					*SIXEL_OPTS
+				} else {
+					emulator.sixel_opts().with_overrides(PREVIEW.sixel_palette, PREVIEW.sixel_dither)
+				},
+				fraction: PREVIEW.sixel_fraction,
+			}),
+			Self::X11 | Self::Wayland | Self::Chafa => Box::new(NonePlacer),
+		}
+	}
+
+	pub(super) async fn place(self, img: DynamicImage, area: Rect) -> Result<Vec<u8>> {
+		let placer = self.placer(&EMULATOR);
+		let mut b = tokio::task::spawn_blocking(move || placer.place(&img, area, *TMUX)).await?;
+		if b.is_empty() {
+			bail!("failed to encode image for {self}");
+		}
+
+		if EMULATOR.needs_cursor_show_after_image() {
+			queue!(b, Show)?;
+		}
+		Ok(b)
+	}
+}
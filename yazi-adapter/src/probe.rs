@@ -0,0 +1,45 @@
+use std::{fmt::{self, Display}, time::Duration};
+
+use crate::Emulator;
+
+/// The emulator told by the CSI probe, and how long each step of it took,
+/// to have concrete numbers when startup is slow.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CsiProbe {
+	pub emulator:        Emulator,
+	// Whether synchronized output (DECSET 2026) is supported
+	pub sync_output:     bool,
+	// The number of Sixel color registers, as reported to XTSMGRAPHICS
+	pub sixel_registers: Option<u16>,
+	// The `name version` reported to XTVERSION
+	pub version:         Option<String>,
+	pub timing:          ProbeTiming,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProbeTiming {
+	// Entering raw mode
+	pub raw_mode: Duration,
+	// Writing all the queries
+	pub write:    Duration,
+	// Waiting for the replies, up to the DA1 after each query
+	pub read:     Duration,
+}
+
+impl ProbeTiming {
+	#[inline]
+	pub fn total(&self) -> Duration { self.raw_mode + self.write + self.read }
+}
+
+impl Display for ProbeTiming {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"raw mode {:?}, write {:?}, read {:?}, total {:?}",
+			self.raw_mode,
+			self.write,
+			self.read,
+			self.total()
+		)
+	}
+}
@@ -0,0 +1,206 @@
+use std::{fmt::{self, Display}, io::{LineWriter, stderr}, time::Duration};
+
+use anyhow::Result;
+use crossterm::{cursor::{RestorePosition, SavePosition}, execute, style::Print, terminal::{disable_raw_mode, enable_raw_mode}};
+use scopeguard::defer;
+use tokio::{io::{AsyncRead, BufReader}, time::Instant};
+use tokio_util::sync::CancellationToken;
+
+use crate::{Adapter, Emulator, Mux};
+
+/// The adapters an emulator is declared to support, next to the ones the
+/// terminal actually acknowledged when queried.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AdapterReport {
+	pub declared:     Vec<Adapter>,
+	// IIP can't be queried, so it's taken as declared, while both KGP variants
+	// answer the same query and are reported as the declared one
+	pub acknowledged: Vec<Adapter>,
+}
+
+impl Emulator {
+	/// Queries the terminal for the adapters it supports, to compare them with
+	/// [`Self::adapters`] when images don't show up.
+	pub fn adapter_report(&self) -> AdapterReport {
+		let resp = Self::query("\x1b_Gi=31,s=1,v=1,a=q,t=d,f=24;AAAA\x1b\\").unwrap_or_default();
+		AdapterReport::from_probe(self.clone().adapters(), &resp)
+	}
+}
+
+impl Emulator {
+	/// Whether the terminal acknowledges a no-op 1x1 image drawn with `adapter`,
+	/// to fall back to another one before relying on it. KGP is asked to
+	/// validate the image without storing it, and an empty Sixel is followed by
+	/// DA1 to see it still listed there. The adapters that can't be
+	/// acknowledged, like IIP, are taken as working.
+	pub fn verify_adapter(&self, adapter: Adapter) -> bool {
+		if Self::verify_probe(adapter).is_none() {
+			return true;
+		} else if !Self::can_probe() {
+			return false;
+		}
+
+		defer! { disable_raw_mode().ok(); }
+		if enable_raw_mode().is_err() {
+			return false;
+		}
+
+		futures::executor::block_on(Self::verify_from(
+			adapter,
+			BufReader::new(tokio::io::stdin()),
+			|s| {
+				Ok(execute!(LineWriter::new(stderr()), SavePosition, Print(Mux::csi(s)), RestorePosition)?)
+			},
+			&CancellationToken::new(),
+			Instant::now() + Duration::from_millis(500),
+		))
+	}
+
+	async fn verify_from(
+		adapter: Adapter,
+		mut reader: impl AsyncRead + Unpin,
+		send: impl FnOnce(&str) -> Result<()>,
+		cancel: &CancellationToken,
+		deadline: Instant,
+	) -> bool {
+		let Some(probe) = Self::verify_probe(adapter) else { return true };
+		if send(&format!("{probe}\x1b[c")).is_err() {
+			return false;
+		}
+
+		let resp = Self::read_until_da1_from(&mut reader, cancel, deadline).await;
+		match adapter {
+			Adapter::Kgp | Adapter::KgpOld => Self::kgp_ok(&resp),
+			_ => Self::da1_sixel(&resp),
+		}
+	}
+
+	#[inline]
+	fn verify_probe(adapter: Adapter) -> Option<&'static str> {
+		match adapter {
+			Adapter::Kgp | Adapter::KgpOld => Some("\x1b_Gi=31,s=1,v=1,a=q,t=d,f=24;AAAA\x1b\\"),
+			// Only the raster attributes, so not even a pixel is drawn
+			Adapter::Sixel => Some("\x1bP0;1;8q\"1;1;1;1\x1b\\"),
+			_ => None,
+		}
+	}
+}
+
+impl AdapterReport {
+	fn from_probe(declared: Vec<Adapter>, resp: &str) -> Self {
+		let mut acknowledged = Vec::with_capacity(declared.len());
+		if Emulator::kgp_ok(resp) {
+			acknowledged.push(declared.iter().copied().find(Self::is_kgp).unwrap_or(Adapter::Kgp));
+		}
+		if declared.contains(&Adapter::Iip) {
+			acknowledged.push(Adapter::Iip);
+		}
+		if Emulator::da1_sixel(resp) {
+			acknowledged.push(Adapter::Sixel);
+		}
+		Self { declared, acknowledged }
+	}
+
+	/// Declared, but not acknowledged by the terminal.
+	pub fn missing(&self) -> Vec<Adapter> {
+		self.declared.iter().filter(|a| !self.acknowledged.contains(a)).copied().collect()
+	}
+
+	/// Acknowledged by the terminal, but not declared.
+	pub fn undeclared(&self) -> Vec<Adapter> {
+		self.acknowledged.iter().filter(|a| !self.declared.contains(a)).copied().collect()
+	}
+
+	#[inline]
+	pub fn is_consistent(&self) -> bool { self.missing().is_empty() && self.undeclared().is_empty() }
+
+	#[inline]
+	fn is_kgp(a: &Adapter) -> bool { matches!(a, Adapter::Kgp | Adapter::KgpOld) }
+}
+
+impl Display for AdapterReport {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let list = |v: &[Adapter]| v.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(", ");
+
+		write!(f, "declared [{}], acknowledged [{}]", list(&self.declared), list(&self.acknowledged))?;
+		if !self.missing().is_empty() {
+			write!(f, ", missing [{}]", list(&self.missing()))?;
+		}
+		if !self.undeclared().is_empty() {
+			write!(f, ", undeclared [{}]", list(&self.undeclared()))?;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use tokio::io::AsyncWriteExt;
+
+	use super::*;
+
+	async fn verify(adapter: Adapter, reply: Option<&str>) -> bool {
+		let (mut tx, rx) = tokio::io::duplex(256);
+		if let Some(reply) = reply {
+			tx.write_all(reply.as_bytes()).await.unwrap();
+			drop(tx);
+		}
+
+		let deadline = Instant::now() + Duration::from_millis(50);
+		Emulator::verify_from(adapter, rx, |_| Ok(()), &CancellationToken::new(), deadline).await
+	}
+
+	#[tokio::test]
+	async fn test_verify_adapter() {
+		assert!(verify(Adapter::Kgp, Some("\x1b_Gi=31;OK\x1b\\\x1b[?62;22c")).await);
+		assert!(verify(Adapter::KgpOld, Some("\x1b_Gi=31;OK\x1b\\\x1b[?62;22c")).await);
+		assert!(!verify(Adapter::Kgp, Some("\x1b_Gi=31;EINVAL:bad\x1b\\\x1b[?62;22c")).await);
+		assert!(!verify(Adapter::Kgp, Some("\x1b[?62;22c")).await);
+
+		assert!(verify(Adapter::Sixel, Some("\x1b[?62;4;22c")).await);
+		assert!(!verify(Adapter::Sixel, Some("\x1b[?62;22c")).await);
+
+		// No reply at all within the timeout, or the reader closed
+		let (_tx, rx) = tokio::io::duplex(256);
+		let deadline = Instant::now() + Duration::from_millis(50);
+		assert!(
+			!Emulator::verify_from(Adapter::Kgp, rx, |_| Ok(()), &CancellationToken::new(), deadline)
+				.await
+		);
+		assert!(!verify(Adapter::Sixel, Some("")).await);
+
+		// Nothing to acknowledge, so nothing is sent
+		let send = |_: &str| panic!("IIP can't be acknowledged");
+		let (_, rx) = tokio::io::duplex(256);
+		assert!(
+			Emulator::verify_from(Adapter::Iip, rx, send, &CancellationToken::new(), Instant::now())
+				.await
+		);
+	}
+
+	#[test]
+	fn test_kitty_without_kgp() {
+		let report = AdapterReport::from_probe(Emulator::Kitty.adapters(), "\x1b[?62;22c");
+		assert_eq!(report.acknowledged, []);
+		assert_eq!(report.missing(), [Adapter::Kgp]);
+		assert!(!report.is_consistent());
+		assert_eq!(report.to_string(), "declared [kgp], acknowledged [], missing [kgp]");
+
+		let report =
+			AdapterReport::from_probe(Emulator::Kitty.adapters(), "\x1b_Gi=31;OK\x1b\\\x1b[?62;22c");
+		assert!(report.is_consistent());
+		assert_eq!(report.to_string(), "declared [kgp], acknowledged [kgp]");
+	}
+
+	#[test]
+	fn test_undeclared() {
+		let report =
+			AdapterReport::from_probe(Emulator::Konsole.adapters(), "\x1b_Gi=31;OK\x1b\\\x1b[?62;4c");
+		assert_eq!(report.acknowledged, [Adapter::KgpOld, Adapter::Sixel]);
+		assert_eq!(report.missing(), []);
+		assert_eq!(report.undeclared(), [Adapter::Sixel]);
+
+		let report = AdapterReport::from_probe(Emulator::WezTerm.adapters(), "\x1b[?65;4;22c");
+		assert!(report.is_consistent());
+	}
+}
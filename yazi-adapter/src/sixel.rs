@@ -3,19 +3,41 @@ use std::{io::Write, path::Path};
 use anyhow::{Result, bail};
 use color_quant::NeuQuant;
 use crossterm::{cursor::MoveTo, queue};
-use image::DynamicImage;
+use image::{DynamicImage, RgbaImage};
 use ratatui::layout::Rect;
-use yazi_config::PREVIEW;
 
-use crate::{CLOSE, ESCAPE, Emulator, Image, START, adapter::Adapter};
+use crate::{Emulator, Image, ImagePlacer, Mux, adapter::Adapter};
 
-pub(super) struct Sixel;
+pub(super) struct Sixel {
+	pub(super) opts:     SixelOpts,
+	pub(super) fraction: u8,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SixelOpts {
+	// Number of color registers to use, including the transparent one
+	pub palette: u16,
+	// Floyd-Steinberg dithering, helps a lot with small palettes
+	pub dither:  bool,
+}
+
+impl Default for SixelOpts {
+	fn default() -> Self { Self { palette: 256, dither: false } }
+}
+
+impl SixelOpts {
+	pub fn with_overrides(mut self, palette: Option<u16>, dither: Option<bool>) -> Self {
+		self.palette = palette.unwrap_or(self.palette);
+		self.dither = dither.unwrap_or(self.dither);
+		self
+	}
+}
 
 impl Sixel {
 	pub(super) async fn image_show(path: &Path, max: Rect) -> Result<Rect> {
 		let img = Image::downscale(path, max).await?;
 		let area = Image::pixel_area((img.width(), img.height()), max);
-		let b = Self::encode(img).await?;
+		let b = Adapter::Sixel.place(img, area).await?;
 
 		Adapter::Sixel.image_hide()?;
 		Adapter::shown_store(area);
@@ -36,52 +58,47 @@ impl Sixel {
 		})
 	}
 
-	async fn encode(img: DynamicImage) -> Result<Vec<u8>> {
+	fn encode(&self, img: &DynamicImage, mux: bool) -> Result<Vec<u8>> {
 		let alpha = img.color().has_alpha();
 		if img.width() == 0 || img.height() == 0 {
 			bail!("image is empty");
 		}
 
-		tokio::task::spawn_blocking(move || {
-			let img = img.into_rgba8();
-			let nq = NeuQuant::new(PREVIEW.sixel_fraction as i32, 256 - alpha as usize, &img);
-
-			let mut buf: Vec<u8> = Vec::with_capacity(1 << 16);
-			write!(buf, "{}P0;1;8q\"1;1;{};{}", START, img.width(), img.height())?;
-
-			// Palette
-			for (i, c) in nq.color_map_rgba().chunks(4).enumerate() {
-				write!(
-					buf,
-					"#{};2;{};{};{}",
-					i + alpha as usize,
-					c[0] as u16 * 100 / 255,
-					c[1] as u16 * 100 / 255,
-					c[2] as u16 * 100 / 255
-				)?;
-			}
-
-			for y in 0..img.height() {
-				let c = (b'?' + (1 << (y % 6))) as char;
-
-				let mut last = 0;
-				let mut repeat = 0usize;
-				for x in 0..img.width() {
-					let pixel = img.get_pixel(x, y).0;
-					let idx = if pixel[3] == 0 { 0 } else { nq.index_of(&pixel) as u8 + alpha as u8 };
+		let (start, escape, close) = Mux::parts(mux);
+		let img = img.to_rgba8();
+		let nq = NeuQuant::new(
+			self.fraction as i32,
+			self.opts.palette.clamp(4, 256) as usize - alpha as usize,
+			&img,
+		);
+		let indices = Self::indices(&img, &nq, alpha, self.opts.dither);
+
+		let mut buf: Vec<u8> = Vec::with_capacity(1 << 16);
+		write!(buf, "{}P0;1;8q\"1;1;{};{}", start, img.width(), img.height())?;
+
+		// Palette
+		for (i, c) in nq.color_map_rgba().chunks(4).enumerate() {
+			write!(
+				buf,
+				"#{};2;{};{};{}",
+				i + alpha as usize,
+				c[0] as u16 * 100 / 255,
+				c[1] as u16 * 100 / 255,
+				c[2] as u16 * 100 / 255
+			)?;
+		}
 
-					if idx == last || repeat == 0 {
-						(last, repeat) = (idx, repeat + 1);
-						continue;
-					}
+		for y in 0..img.height() {
+			let c = (b'?' + (1 << (y % 6))) as char;
 
-					if repeat > 1 {
-						write!(buf, "#{last}!{repeat}{c}")?;
-					} else {
-						write!(buf, "#{last}{c}")?;
-					}
+			let mut last = 0;
+			let mut repeat = 0usize;
+			for x in 0..img.width() {
+				let idx = indices[(y * img.width() + x) as usize];
 
-					(last, repeat) = (idx, 1);
+				if idx == last || repeat == 0 {
+					(last, repeat) = (idx, repeat + 1);
+					continue;
 				}
 
 				if repeat > 1 {
@@ -90,15 +107,98 @@ impl Sixel {
 					write!(buf, "#{last}{c}")?;
 				}
 
-				write!(buf, "$")?;
-				if y % 6 == 5 {
-					write!(buf, "-")?;
+				(last, repeat) = (idx, 1);
+			}
+
+			if repeat > 1 {
+				write!(buf, "#{last}!{repeat}{c}")?;
+			} else {
+				write!(buf, "#{last}{c}")?;
+			}
+
+			write!(buf, "$")?;
+			if y % 6 == 5 {
+				write!(buf, "-")?;
+			}
+		}
+
+		write!(buf, "{}\\{}", escape, close)?;
+		Ok(buf)
+	}
+
+	// Maps each pixel to its color register, 0 being transparent if `alpha`
+	fn indices(img: &RgbaImage, nq: &NeuQuant, alpha: bool, dither: bool) -> Vec<u8> {
+		if !dither {
+			return img
+				.pixels()
+				.map(|p| if p[3] == 0 { 0 } else { nq.index_of(&p.0) as u8 + alpha as u8 })
+				.collect();
+		}
+
+		let (w, h) = (img.width() as usize, img.height() as usize);
+		let mut px: Vec<[f32; 3]> =
+			img.pixels().map(|p| [p[0] as f32, p[1] as f32, p[2] as f32]).collect();
+		let mut indices = vec![0; w * h];
+
+		for y in 0..h {
+			for x in 0..w {
+				let i = y * w + x;
+				let a = img.as_raw()[i * 4 + 3];
+				if a == 0 {
+					continue;
+				}
+
+				let [r, g, b] = px[i].map(|c| c.round().clamp(0.0, 255.0) as u8);
+				let idx = nq.index_of(&[r, g, b, a]);
+				indices[i] = idx as u8 + alpha as u8;
+
+				let Some(c) = nq.lookup(idx) else { continue };
+				let err = [0, 1, 2].map(|k| px[i][k] - c[k] as f32);
+				let mut spread = |x: usize, y: usize, f: f32| {
+					if x < w && y < h {
+						let p = &mut px[y * w + x];
+						(0..3).for_each(|k| p[k] += err[k] * f);
+					}
+				};
+
+				spread(x + 1, y, 7.0 / 16.0);
+				if x > 0 {
+					spread(x - 1, y + 1, 3.0 / 16.0);
 				}
+				spread(x, y + 1, 5.0 / 16.0);
+				spread(x + 1, y + 1, 1.0 / 16.0);
 			}
+		}
+		indices
+	}
+}
 
-			write!(buf, "{}\\{}", ESCAPE, CLOSE)?;
-			Ok(buf)
-		})
-		.await?
+impl ImagePlacer for Sixel {
+	// Drawn at the cursor, so `rect` isn't needed
+	fn place(&self, img: &DynamicImage, _: Rect, mux: bool) -> Vec<u8> {
+		self.encode(img, mux).unwrap_or_default()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use image::RgbImage;
+
+	use super::*;
+
+	#[test]
+	fn test_place() {
+		let sixel = Sixel { opts: SixelOpts::default(), fraction: 15 };
+		let img = DynamicImage::ImageRgb8(RgbImage::new(3, 2));
+		let b = String::from_utf8(sixel.place(&img, Rect::default(), false)).unwrap();
+		assert!(b.starts_with("\x1bP0;1;8q\"1;1;3;2#0;2;"));
+		assert!(b.ends_with("$\x1b\\"));
+
+		let b = String::from_utf8(sixel.place(&img, Rect::default(), true)).unwrap();
+		assert!(b.starts_with("\x1bPtmux;\x1b\x1bP0;1;8q\"1;1;3;2"));
+		assert!(b.ends_with("$\x1b\x1b\\\x1b\\"));
+
+		let empty = DynamicImage::ImageRgb8(RgbImage::new(0, 0));
+		assert!(sixel.place(&empty, Rect::default(), false).is_empty());
 	}
 }
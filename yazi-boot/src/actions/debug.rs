@@ -21,11 +21,20 @@ impl Actions {
 
 		writeln!(s, "\nEmulator")?;
 		writeln!(s, "    Emulator.via_env: {:?}", yazi_adapter::Emulator::via_env())?;
-		writeln!(s, "    Emulator.via_csi: {:?}", yazi_adapter::Emulator::via_csi())?;
+		let probe = yazi_adapter::Emulator::probe_csi();
+		writeln!(s, "    Emulator.via_csi: {:?}", probe.as_ref().map(|p| &p.emulator))?;
+		if let Ok(p) = &probe {
+			writeln!(s, "    Emulator.timing : {}", p.timing)?;
+		}
 		writeln!(s, "    Emulator.detect : {:?}", yazi_adapter::Emulator::detect())?;
 
 		writeln!(s, "\nAdapter")?;
-		writeln!(s, "    Adapter.matches: {:?}", yazi_adapter::Adapter::matches())?;
+		writeln!(
+			s,
+			"    Adapter.matches: {:?}",
+			yazi_adapter::Adapter::matches(yazi_adapter::Emulator::detect())
+		)?;
+		writeln!(s, "    Adapter.report : {}", yazi_adapter::Emulator::detect().adapter_report())?;
 
 		writeln!(s, "\nDesktop")?;
 		writeln!(s, "    XDG_SESSION_TYPE           : {:?}", env::var_os("XDG_SESSION_TYPE"))?;
@@ -48,6 +57,8 @@ impl Actions {
 		writeln!(s, "    VISUAL             : {:?}", env::var_os("VISUAL"))?;
 		writeln!(s, "    YAZI_FILE_ONE      : {:?}", env::var_os("YAZI_FILE_ONE"))?;
 		writeln!(s, "    YAZI_CONFIG_HOME   : {:?}", env::var_os("YAZI_CONFIG_HOME"))?;
+		writeln!(s, "    YAZI_FORCE_PROBE   : {:?}", env::var_os("YAZI_FORCE_PROBE"))?;
+		writeln!(s, "    YAZI_NVIM_GEOMETRY : {:?}", env::var_os("YAZI_NVIM_GEOMETRY"))?;
 
 		writeln!(s, "\nText Opener")?;
 		writeln!(
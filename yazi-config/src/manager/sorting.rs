@@ -9,12 +9,40 @@ pub enum SortBy {
 	#[default]
 	None,
 	Mtime,
+	// Not parsed from the config, as nothing collects the mtimes inside
+	// directories yet, only an embedder updating them on `Files` can use it
+	DeepMtime,
 	Btime,
 	Extension,
+	ExtensionFrequency,
 	Alphabetical,
 	Natural,
 	Size,
 	Random,
+	LinkTarget,
+	SizeBucket,
+	Recent,
+	Root,
+	// Not parsed from the config, as nothing records the visits yet, only an
+	// embedder updating them on `Files` can use it
+	Recency,
+	Parent,
+	Chronological,
+	Mime,
+	// Not parsed from the config, as nothing arranges the files yet, only an
+	// embedder setting the order on `Files` can use it
+	Manual,
+	// Neither is this, as Yazi doesn't read the git status of the files yet
+	Git,
+	// Nor this, as Yazi doesn't count the entries of directories yet
+	ChildCount,
+	Label,
+}
+
+impl SortBy {
+	// Whether the directory sizes have to be calculated for this sort
+	#[inline]
+	pub fn uses_size(self) -> bool { matches!(self, Self::Size | Self::SizeBucket) }
 }
 
 impl FromStr for SortBy {
@@ -26,10 +54,19 @@ impl FromStr for SortBy {
 			"mtime" => Self::Mtime,
 			"btime" => Self::Btime,
 			"extension" => Self::Extension,
+			"extension-frequency" => Self::ExtensionFrequency,
 			"alphabetical" => Self::Alphabetical,
 			"natural" => Self::Natural,
 			"size" => Self::Size,
 			"random" => Self::Random,
+			"link-target" => Self::LinkTarget,
+			"size-bucket" => Self::SizeBucket,
+			"recent" => Self::Recent,
+			"root" => Self::Root,
+			"parent" => Self::Parent,
+			"chronological" => Self::Chronological,
+			"mime" => Self::Mime,
+			"label" => Self::Label,
 			_ => bail!("invalid sort_by value: {s}"),
 		})
 	}
@@ -46,12 +83,26 @@ impl Display for SortBy {
 		f.write_str(match self {
 			Self::None => "none",
 			Self::Mtime => "mtime",
+			Self::DeepMtime => "deep-mtime",
 			Self::Btime => "btime",
 			Self::Extension => "extension",
+			Self::ExtensionFrequency => "extension-frequency",
 			Self::Alphabetical => "alphabetical",
 			Self::Natural => "natural",
 			Self::Size => "size",
 			Self::Random => "random",
+			Self::LinkTarget => "link-target",
+			Self::SizeBucket => "size-bucket",
+			Self::Recent => "recent",
+			Self::Root => "root",
+			Self::Recency => "recency",
+			Self::Parent => "parent",
+			Self::Chronological => "chronological",
+			Self::Mime => "mime",
+			Self::Manual => "manual",
+			Self::Git => "git",
+			Self::ChildCount => "child-count",
+			Self::Label => "label",
 		})
 	}
 }
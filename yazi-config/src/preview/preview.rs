@@ -24,6 +24,8 @@ pub struct Preview {
 	pub image_filter:   String,
 	pub image_quality:  u8,
 	pub sixel_fraction: u8,
+	pub sixel_palette:  Option<u16>,
+	pub sixel_dither:   Option<bool>,
 
 	pub ueberzug_scale:  f32,
 	pub ueberzug_offset: (f32, f32, f32, f32),
@@ -69,6 +71,9 @@ impl FromStr for Preview {
 			image_quality:  u8,
 			#[validate(range(min = 10, max = 20))]
 			sixel_fraction: u8,
+			#[validate(range(min = 4, max = 256))]
+			sixel_palette:  Option<u16>,
+			sixel_dither:   Option<bool>,
 
 			ueberzug_scale:  f32,
 			ueberzug_offset: (f32, f32, f32, f32),
@@ -93,6 +98,8 @@ impl FromStr for Preview {
 			image_filter: preview.image_filter,
 			image_quality: preview.image_quality,
 			sixel_fraction: preview.sixel_fraction,
+			sixel_palette: preview.sixel_palette,
+			sixel_dither: preview.sixel_dither,
 
 			ueberzug_scale: preview.ueberzug_scale,
 			ueberzug_offset: preview.ueberzug_offset,
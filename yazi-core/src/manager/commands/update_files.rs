@@ -75,7 +75,7 @@ impl Manager {
 
 	fn update_current(tab: &mut Tab, op: Cow<FilesOp>, tasks: &Tasks) {
 		let hovered = tab.hovered().filter(|_| tab.current.tracing).map(|h| h.urn_owned());
-		let calc = !matches!(*op, FilesOp::Size(..) | FilesOp::Deleting(..));
+		let calc = !matches!(*op, FilesOp::Size(..) | FilesOp::Label(..) | FilesOp::Deleting(..));
 
 		let foreign = matches!(op, Cow::Borrowed(_));
 		if !tab.current.update(op.into_owned()) {
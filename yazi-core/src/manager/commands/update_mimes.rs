@@ -51,6 +51,14 @@ impl Manager {
 			.cloned()
 			.collect();
 
+		let mimes = self
+			.current()
+			.files
+			.iter()
+			.filter_map(|f| Some((f.urn_owned(), updates.get(&f.url)?.clone())))
+			.collect();
+		self.current_mut().files.update_mimes(mimes);
+
 		let repeek = self.hovered().is_some_and(|f| updates.contains_key(&f.url));
 		self.mimetype.extend(updates);
 
@@ -42,11 +42,12 @@ impl Config {
 	#[inline]
 	pub(super) fn sorter(&self) -> FilesSorter {
 		FilesSorter {
-			by:        self.sort_by,
+			by: self.sort_by,
 			sensitive: self.sort_sensitive,
-			reverse:   self.sort_reverse,
+			reverse: self.sort_reverse,
 			dir_first: self.sort_dir_first,
-			translit:  self.sort_translit,
+			translit: self.sort_translit,
+			..Default::default()
 		}
 	}
 }
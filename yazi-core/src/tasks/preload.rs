@@ -63,7 +63,10 @@ impl Tasks {
 	}
 
 	pub fn prework_sorted(&self, targets: &Files) {
-		if targets.sorter().by != SortBy::Size {
+		if targets.sorter().uses(SortBy::Label) {
+			self.prework_labels(targets);
+		}
+		if !targets.sorter().uses_size() {
 			return;
 		}
 
@@ -86,4 +89,27 @@ impl Tasks {
 
 		self.scheduler.prework_size(targets);
 	}
+
+	// Reads the labels of the files not read yet, only while sorting by them, as
+	// it takes a syscall for each file
+	fn prework_labels(&self, targets: &Files) {
+		let targets: Vec<_> = {
+			let loading = self.scheduler.prework.label_loading.read();
+			targets
+				.iter()
+				.filter(|f| !targets.labels.contains_key(f.urn()) && !loading.contains(&f.url))
+				.map(|f| &f.url)
+				.collect()
+		};
+		if targets.is_empty() {
+			return;
+		}
+
+		let mut loading = self.scheduler.prework.label_loading.write();
+		for &target in &targets {
+			loading.insert(target.clone());
+		}
+
+		self.scheduler.prework_label(targets);
+	}
 }
@@ -22,7 +22,12 @@ async fn main() -> anyhow::Result<()> {
 
 	yazi_config::init()?;
 
+	let probe = tokio::spawn(Signals::cancel_probe());
 	yazi_adapter::init();
+	probe.abort();
+	if yazi_adapter::Emulator::probe_cancelled() {
+		return Ok(());
+	}
 
 	yazi_boot::init();
 
@@ -23,6 +23,27 @@ impl Signals {
 		self.tx.send((true, cb)).ok();
 	}
 
+	// Cancels the emulator probe on termination during startup, so a terminal that
+	// never answers it can't hold the process up until the probe times out.
+	pub(super) async fn cancel_probe() {
+		#[cfg(unix)]
+		{
+			use libc::{SIGHUP, SIGQUIT, SIGTERM};
+			let Ok(mut sys) = signal_hook_tokio::Signals::new([SIGHUP, SIGTERM, SIGQUIT]) else {
+				return;
+			};
+			if sys.next().await.is_none() {
+				return;
+			}
+		}
+		#[cfg(windows)]
+		if tokio::signal::ctrl_c().await.is_err() {
+			return;
+		}
+
+		yazi_adapter::Emulator::cancel_probe();
+	}
+
 	#[cfg(unix)]
 	#[inline]
 	fn handle_sys(n: libc::c_int) -> bool {
@@ -4,7 +4,7 @@ use anyhow::Result;
 use crossterm::{event::{DisableBracketedPaste, EnableBracketedPaste, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags}, execute, queue, style::Print, terminal::{EnterAlternateScreen, LeaveAlternateScreen, SetTitle, disable_raw_mode, enable_raw_mode}};
 use cursor::RestoreCursor;
 use ratatui::{CompletedFrame, Frame, Terminal, backend::CrosstermBackend, buffer::Buffer, layout::Rect};
-use yazi_adapter::{Emulator, Mux};
+use yazi_adapter::{EMULATOR, Emulator, Mux};
 use yazi_config::{INPUT, MANAGER};
 
 static CSI_U: AtomicBool = AtomicBool::new(false);
@@ -36,6 +36,8 @@ impl Term {
 			mouse::SetMouse(true),
 		)?;
 
+		EMULATOR.reset_all_images().ok();
+
 		let da = futures::executor::block_on(Emulator::read_until_da1());
 		CSI_U.store(da.contains("\x1b[?0u"), Ordering::Relaxed);
 		BLINK.store(da.contains("\x1b[?12;1$y"), Ordering::Relaxed);
@@ -1,10 +1,10 @@
-use std::{collections::{HashMap, HashSet}, mem, ops::Deref, sync::atomic::Ordering};
+use std::{collections::{HashMap, HashSet}, mem, ops::Deref, sync::atomic::Ordering, time::SystemTime};
 
 use tokio::{fs::{self, DirEntry}, select, sync::mpsc::{self, UnboundedReceiver}};
 use yazi_config::{MANAGER, manager::SortBy};
-use yazi_shared::fs::{Cha, FILES_TICKET, File, FilesOp, Url, Urn, UrnBuf, maybe_exists};
+use yazi_shared::fs::{Cha, FILES_TICKET, File, FilesOp, Label, Url, Urn, UrnBuf, maybe_exists};
 
-use super::{FilesSorter, Filter};
+use super::{FilesSorter, Filter, GitChange, SortKeys, SortMeta};
 
 pub struct Files {
 	hidden:       Vec<File>,
@@ -13,9 +13,22 @@ pub struct Files {
 	version:      u64,
 	pub revision: u64,
 
-	pub sizes: HashMap<UrnBuf, u64>,
+	pub sizes:       HashMap<UrnBuf, u64>,
+	pub visits:      HashMap<UrnBuf, SystemTime>,
+	pub mimes:       HashMap<UrnBuf, String>,
+	// The newest mtime anywhere inside each directory
+	pub deep_mtimes: HashMap<UrnBuf, SystemTime>,
+	// Finder color labels, read only while sorting by them, `None` for no label
+	pub labels:      HashMap<UrnBuf, Option<Label>>,
+	// Positions of the files arranged by hand, e.g. by an embedder
+	manual_order:    HashMap<UrnBuf, u32>,
+	// How each file differs in its git work tree, e.g. from a plugin
+	git_changes:     HashMap<UrnBuf, GitChange>,
+	// How many entries each directory contains
+	child_counts:    HashMap<UrnBuf, usize>,
 
 	sorter:      FilesSorter,
+	keys:        SortKeys,
 	filter:      Option<Filter>,
 	show_hidden: bool,
 }
@@ -29,9 +42,17 @@ impl Default for Files {
 			version:  Default::default(),
 			revision: Default::default(),
 
-			sizes: Default::default(),
+			sizes:        Default::default(),
+			visits:       Default::default(),
+			mimes:        Default::default(),
+			deep_mtimes:  Default::default(),
+			labels:       Default::default(),
+			manual_order: Default::default(),
+			git_changes:  Default::default(),
+			child_counts: Default::default(),
 
 			sorter:      Default::default(),
+			keys:        Default::default(),
 			filter:      Default::default(),
 			show_hidden: MANAGER.show_hidden,
 		}
@@ -155,12 +176,95 @@ impl Files {
 			return;
 		}
 
-		if self.sorter.by == SortBy::Size {
+		if self.sorter.uses_size() {
 			self.revision += 1;
 		}
 		self.sizes.extend(sizes);
 	}
 
+	pub fn update_visits(&mut self, visits: HashMap<UrnBuf, SystemTime>) {
+		if visits.is_empty() {
+			return;
+		}
+
+		if self.sorter.uses(SortBy::Recency) {
+			self.revision += 1;
+		}
+		self.visits.extend(visits);
+	}
+
+	pub fn update_mimes(&mut self, mimes: HashMap<UrnBuf, String>) {
+		if mimes.is_empty() {
+			return;
+		}
+
+		if self.sorter.uses(SortBy::Mime) {
+			self.revision += 1;
+		}
+		self.mimes.extend(mimes);
+	}
+
+	pub fn update_deep_mtimes(&mut self, mtimes: HashMap<UrnBuf, SystemTime>) {
+		if mtimes.is_empty() {
+			return;
+		}
+
+		if self.sorter.uses(SortBy::DeepMtime) {
+			self.revision += 1;
+		}
+		self.deep_mtimes.extend(mtimes);
+	}
+
+	pub fn update_labels(&mut self, labels: HashMap<UrnBuf, Option<Label>>) {
+		if labels.is_empty() {
+			return;
+		}
+
+		if self.sorter.uses(SortBy::Label) {
+			self.revision += 1;
+		}
+		self.labels.extend(labels);
+	}
+
+	/// Replaces the arrangement followed by the manual sort, which only an
+	/// embedder can provide as nothing in Yazi arranges the files itself.
+	pub fn set_manual_order(&mut self, order: HashMap<UrnBuf, u32>) {
+		if self.manual_order == order {
+			return;
+		}
+
+		if self.sorter.uses(SortBy::Manual) {
+			self.revision += 1;
+		}
+		self.manual_order = order;
+	}
+
+	/// Replaces the git changes the git sort ranks files by, which only an
+	/// embedder can provide as Yazi doesn't read the git status itself.
+	pub fn set_git_changes(&mut self, changes: HashMap<UrnBuf, GitChange>) {
+		if self.git_changes == changes {
+			return;
+		}
+
+		if self.sorter.uses(SortBy::Git) {
+			self.revision += 1;
+		}
+		self.git_changes = changes;
+	}
+
+	/// Replaces the entry counts of directories the child count sort goes by,
+	/// which only an embedder can provide as Yazi doesn't count them itself.
+	pub fn set_child_counts(&mut self, counts: HashMap<UrnBuf, usize>) {
+		if self.child_counts == counts {
+			return;
+		}
+
+		if self.sorter.uses(SortBy::ChildCount) {
+			self.revision += 1;
+		}
+		self.child_counts = counts;
+	}
+
 	pub fn update_ioerr(&mut self) {
 		self.ticket = FILES_TICKET.fetch_add(1, Ordering::Relaxed);
 		self.hidden.clear();
@@ -325,7 +429,7 @@ impl Files {
 		}
 
 		self.version = self.revision;
-		self.sorter.sort(&mut self.items, &self.sizes);
+		self.sort();
 		true
 	}
 
@@ -362,6 +466,24 @@ impl Files {
 		}
 	}
 
+	#[inline]
+	fn sort(&mut self) {
+		self.sorter.sort_cached(
+			&mut self.items,
+			&SortMeta {
+				sizes:       Some(&self.sizes),
+				visits:      Some(&self.visits),
+				mimes:       Some(&self.mimes),
+				deep_mtimes: Some(&self.deep_mtimes),
+				labels:      Some(&self.labels),
+				manual:      Some(&self.manual_order),
+				git:         Some(&self.git_changes),
+				counts:      Some(&self.child_counts),
+			},
+			&mut self.keys,
+		);
+	}
+
 	// --- Filter
 	#[inline]
 	pub fn filter(&self) -> Option<&Filter> { self.filter.as_ref() }
@@ -379,14 +501,14 @@ impl Files {
 			self.hidden = hidden;
 			if !items.is_empty() {
 				self.items.extend(items);
-				self.sorter.sort(&mut self.items, &self.sizes);
+				self.sort();
 			}
 			return true;
 		}
 
 		let it = mem::take(&mut self.items).into_iter().chain(mem::take(&mut self.hidden));
 		(self.hidden, self.items) = self.split_files(it);
-		self.sorter.sort(&mut self.items, &self.sizes);
+		self.sort();
 		true
 	}
 
@@ -0,0 +1,10 @@
+/// How a file differs in its git work tree, as supplied by the caller, e.g.
+/// from `git status`. Ordered from the most to the least severe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GitChange {
+	Conflicted,
+	Modified,
+	Renamed,
+	Added,
+	Untracked,
+}
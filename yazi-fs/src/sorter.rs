@@ -1,106 +1,2307 @@
-use std::{cmp::Ordering, collections::HashMap, mem};
+use std::{borrow::Cow, cmp::{Ordering, Reverse}, collections::{HashMap, HashSet}, ffi::{OsStr, OsString}, hash::{DefaultHasher, Hash, Hasher}, mem, ops::Deref, path::{Component, Path}, sync::Arc, time::{Duration, SystemTime, UNIX_EPOCH}};
 
+use unicode_normalization::{UnicodeNormalization, is_nfc};
 use yazi_config::manager::SortBy;
-use yazi_shared::{LcgRng, fs::{File, UrnBuf}, natsort, translit::Transliterator};
+use yazi_shared::{LcgRng, NatsortOpts, fs::{File, Label, UrnBuf}, natsort_with, natural_key, translit::Transliterator};
 
-#[derive(Clone, Copy, Default, PartialEq)]
+use crate::GitChange;
+
+#[derive(Clone, Default, PartialEq)]
 pub struct FilesSorter {
-	pub by:        SortBy,
-	pub sensitive: bool,
-	pub reverse:   bool,
-	pub dir_first: bool,
-	pub translit:  bool,
+	pub by:                 SortBy,
+	pub sensitive:          bool,
+	pub reverse:            bool,
+	pub dir_first:          bool,
+	pub translit:           bool,
+	// Keep the relative order of items with equal keys between refreshes,
+	// at the cost of the extra allocation and slower merging of a stable sort.
+	pub stable:             bool,
+	// Use the mtime for files without a btime, which many Linux filesystems
+	// don't report, instead of letting them all tie.
+	pub btime_fallback:     bool,
+	// Extensions sorted first in this order by the extension sort, e.g. `["rs", "toml"]`,
+	// the rest follow alphabetically.
+	pub ext_priority:       Vec<String>,
+	// Without `dir_first`, compare files by their stem so that a directory
+	// "foo" sorts next to "foo.txt" rather than apart from it.
+	pub dir_anchor:         bool,
+	// Keys compared in turn when the primary one ties, before falling back to the name.
+	pub tie_breaks:         Vec<TieBreak>,
+	// Sort directories whose size hasn't been calculated yet as empty,
+	// rather than by their inode size, which is meaningless to users.
+	pub dir_unknown_zero:   bool,
+	// Compare names in NFC, so that the decomposed names macOS tends to produce
+	// sort the same as their composed forms.
+	pub normalize:          bool,
+	// Keep the files created within this window of time on top, newest first,
+	// e.g. for a downloads directory.
+	pub recent_window:      Option<Duration>,
+	// Keep the files modified after this time on top, newest first, e.g. since yazi
+	// started, to see what changed in the meantime.
+	pub session_start:      Option<SystemTime>,
+	// Seed the random sort from the directory and its number of files, so that it
+	// shuffles the same way every time until the directory changes.
+	pub shuffle_seeded:     bool,
+	// Ignored at the start and end of names when comparing them, e.g. "snapshot-" and ".tar"
+	pub strip_prefix:       Option<String>,
+	pub strip_suffix:       Option<String>,
+	// Natural sort numbers after a `-` as negative, see `NatsortOpts::signed_numbers`
+	pub signed_numbers:     bool,
+	// Natural sort names starting with a digit last, see `NatsortOpts::digits_last`
+	pub digits_last:        bool,
+	// Natural sort digits grouped by this separator as one number, like "1,000",
+	// see `NatsortOpts::group_separator`
+	pub group_separator:    Option<u8>,
+	// Compare names alphabetically in plain byte order, uppercase before lowercase,
+	// regardless of `sensitive`
+	pub ascii_case:         bool,
+	// Sort names ending in a numeric extension, like rotated logs "app.log.1" and
+	// "app.log.10", by that number after the name without it, when sorting alphabetically
+	pub log_rotation:       bool,
+	// Ignore trailing whitespace and zero-width characters when comparing names,
+	// which files synced from Windows often end up with.
+	pub trim_invisible:     bool,
+	// Group temporary and partial files last, e.g. unfinished downloads and editor
+	// backups, matched by `temp_patterns` or `Self::TEMP_PATTERNS` if not set.
+	pub temp_last:          bool,
+	pub temp_patterns:      Option<Vec<String>>,
+	// Group broken symlinks first, for cleaning up dead links, while valid links
+	// sort together with the other files.
+	pub broken_links_first: bool,
+	// Files kept on top, in the order of the rest of the sort, even before directories
+	pub pinned:             Arc<HashSet<UrnBuf>>,
+	// Files matching a soft filter, kept above the rest, after the pinned ones
+	pub match_set:          Arc<HashSet<UrnBuf>>,
+	// Group files by their parent directory before the rest of the sort, for flattened
+	// recursive views, while directories keep their own order with `dir_first`
+	pub files_by_parent:    bool,
+	// Which size of files the size sorts compare
+	pub size_mode:          SizeMode,
+	// Keep sidecar files next to their primary when sorting by name, e.g. "IMG_001.CR2.xmp"
+	// and "IMG_001.xmp" after "IMG_001.CR2", told by `sidecar_suffixes` or
+	// `Self::SIDECAR_SUFFIXES` if not set.
+	pub sidecars_adjacent:  bool,
+	pub sidecar_suffixes:   Option<Vec<String>>,
+	// Compare names by the position of their characters in this table when sorting by name,
+	// e.g. for a custom alphabet, the characters not in it last in Unicode order
+	pub collation:          Option<Arc<Vec<char>>>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SizeMode {
+	// The length of the content
+	#[default]
+	Apparent,
+	// The space taken on disk, smaller for sparse and compressed files
+	Allocated,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TieBreak {
+	pub by:      SortBy,
+	// Descending, relative to the overall direction of `reverse`
+	pub reverse: bool,
 }
 
 impl FilesSorter {
-	pub(super) fn sort(&self, items: &mut Vec<File>, sizes: &HashMap<UrnBuf, u64>) {
+	/// Suffixes of sidecar files, compared case-insensitively.
+	pub const SIDECAR_SUFFIXES: &[&str] = &[".xmp", ".aae", ".thm"];
+	/// Names of temporary files, `*` matching anything, compared ignoring case.
+	pub const TEMP_PATTERNS: &[&str] = &["*.part", "*.crdownload", "*~", "*.tmp", "#*#"];
+
+	#[inline]
+	pub fn sort(&self, items: &mut Vec<File>, meta: &SortMeta) {
+		self.sort_with(items, meta, SortFlags::default());
+	}
+
+	/// Sorts with some of the toggles overridden for this call only.
+	pub fn sort_with(&self, items: &mut Vec<File>, meta: &SortMeta, flags: SortFlags) {
+		let meta = Meta { known: *meta, ext_counts: None };
+		self.sorting(flags).sort_at(items, meta, None, SystemTime::now());
+	}
+
+	/// Same as `sort()`, but reuses the name keys computed by the previous sorts
+	/// with the same options from `keys`, and stores the new ones there.
+	pub(super) fn sort_cached(&self, items: &mut Vec<File>, meta: &SortMeta, keys: &mut SortKeys) {
+		let meta = Meta { known: *meta, ext_counts: None };
+		self.sorting(SortFlags::default()).sort_at(items, meta, Some(keys), SystemTime::now());
+	}
+
+	/// Sorts by an integer key computed by the caller, e.g. for views driven by a
+	/// plugin, in place of `by` but still grouped by `promote()` and ordered per
+	/// `reverse`. Files with the same key are ordered naturally by their names.
+	pub fn sort_by_key_fn(&self, items: &mut Vec<File>, f: impl Fn(&File) -> i64) {
 		if items.is_empty() {
 			return;
 		}
 
-		let by_alphabetical = |a: &File, b: &File| {
-			if self.sensitive {
-				self.cmp(a.name(), b.name(), self.promote(a, b))
-			} else {
-				self.cmp(a.name().to_ascii_uppercase(), b.name().to_ascii_uppercase(), self.promote(a, b))
+		let keys: Vec<_> = items.iter().map(f).collect();
+		let cutoff = self.recent_window.and_then(|w| SystemTime::now().checked_sub(w));
+		let s = self.sorting(SortFlags::default());
+		let meta = Meta { known: SortMeta::default(), ext_counts: None };
+		s.sort_indexed(items, |a, b, i, j| {
+			s.promote(a, b, cutoff).then_with(|| match keys[i].cmp(&keys[j]) {
+				Ordering::Equal => s.cmp_natural(a, b, meta),
+				ord => s.directed(ord),
+			})
+		});
+	}
+
+	/// Sorts like `sort()` and returns the new index of `focus`, for the view to
+	/// keep it in place after files came or went. `None` if it's no longer among
+	/// the items. The neighbors it had are kept only as far as the sort
+	/// [`Self::is_deterministic`], which a random one isn't.
+	pub fn sort_preserving(
+		&self,
+		items: &mut Vec<File>,
+		meta: &SortMeta,
+		focus: &UrnBuf,
+	) -> Option<usize> {
+		self.sort(items, meta);
+		items.iter().position(|f| f.urn() == focus.as_urn())
+	}
+
+	/// Whether sorting the same items again yields the same order, which isn't
+	/// the case for an unseeded random sort, nor for `recent_window` over time.
+	pub fn is_deterministic(&self) -> bool {
+		(self.by != SortBy::Random || self.shuffle_seeded) && self.recent_window.is_none()
+	}
+
+	/// A preset for media folders: by extension, largest first within each.
+	pub fn preset_media() -> Self {
+		Self {
+			by: SortBy::Extension,
+			tie_breaks: vec![TieBreak { by: SortBy::Size, reverse: true }],
+			..Default::default()
+		}
+	}
+
+	/// A preset for cleaning up: grouped by MIME type, largest first within each.
+	pub fn preset_cleanup() -> Self {
+		Self {
+			by: SortBy::Mime,
+			tie_breaks: vec![TieBreak { by: SortBy::Size, reverse: true }],
+			..Default::default()
+		}
+	}
+
+	/// A preset for reviewing work in a git repository: changed files first, the
+	/// most severe changes before the rest, naturally by name within each.
+	pub fn preset_git() -> Self {
+		Self {
+			by: SortBy::Git,
+			tie_breaks: vec![TieBreak { by: SortBy::Natural, reverse: false }],
+			..Default::default()
+		}
+	}
+
+	/// Whether the sort or any of its tie-breaks need the directory sizes.
+	#[inline]
+	pub fn uses_size(&self) -> bool {
+		self.by.uses_size() || self.tie_breaks.iter().any(|t| t.by.uses_size())
+	}
+
+	/// Whether the sort or any of its tie-breaks go by `by`.
+	#[inline]
+	pub fn uses(&self, by: SortBy) -> bool {
+		self.by == by || self.tie_breaks.iter().any(|t| t.by == by)
+	}
+
+	#[inline]
+	fn sorting(&self, flags: SortFlags) -> Sorting<'_> {
+		Sorting {
+			sorter:    self,
+			sensitive: flags.sensitive.unwrap_or(self.sensitive),
+			reverse:   flags.reverse.unwrap_or(self.reverse),
+			dir_first: flags.dir_first.unwrap_or(self.dir_first),
+			translit:  flags.translit.unwrap_or(self.translit),
+			stable:    flags.stable.unwrap_or(self.stable),
+		}
+	}
+}
+
+/// Overrides of the [`FilesSorter`] toggles, `None` keeps the sorter's own.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SortFlags {
+	pub sensitive: Option<bool>,
+	pub reverse:   Option<bool>,
+	pub dir_first: Option<bool>,
+	pub translit:  Option<bool>,
+	pub stable:    Option<bool>,
+}
+
+/// What's known about the files beyond their own metadata, for the sorts that
+/// go by it. The files missing from a map, or all of them if it's `None`, sort
+/// as if nothing is known about them.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SortMeta<'a> {
+	// Calculated sizes of directories
+	pub sizes:       Option<&'a HashMap<UrnBuf, u64>>,
+	// When each file was last visited
+	pub visits:      Option<&'a HashMap<UrnBuf, SystemTime>>,
+	pub mimes:       Option<&'a HashMap<UrnBuf, String>>,
+	// The newest mtime anywhere inside each directory
+	pub deep_mtimes: Option<&'a HashMap<UrnBuf, SystemTime>>,
+	// Finder color labels, `None` for the files read to have none
+	pub labels:      Option<&'a HashMap<UrnBuf, Option<Label>>>,
+	// Where each file is arranged, lowest first
+	pub manual:      Option<&'a HashMap<UrnBuf, u32>>,
+	pub git:         Option<&'a HashMap<UrnBuf, GitChange>>,
+	// Number of entries in each directory
+	pub counts:      Option<&'a HashMap<UrnBuf, usize>>,
+}
+
+// The value known for `f` in `map`, if any
+#[inline]
+fn known<'a, V>(map: Option<&'a HashMap<UrnBuf, V>>, f: &File) -> Option<&'a V> {
+	map?.get(f.urn())
+}
+
+// What the caller knows about the files, plus what the sort gathers from them
+#[derive(Clone, Copy)]
+struct Meta<'a> {
+	known:      SortMeta<'a>,
+	// Number of files with each extension, counted for the extension frequency sort
+	ext_counts: Option<&'a HashMap<Option<OsString>, usize>>,
+}
+
+/// Name keys kept between sorts, whose order matches the one of the names under
+/// the options they were computed with, so only new files need computing them.
+#[derive(Default)]
+pub struct SortKeys {
+	opts: Option<KeyOpts>,
+	keys: HashMap<UrnBuf, Box<[u8]>>,
+}
+
+// The options a cached key depends on
+#[derive(Clone, PartialEq)]
+struct KeyOpts {
+	by:             SortBy,
+	bytewise:       bool,
+	translit:       bool,
+	normalize:      bool,
+	trim_invisible: bool,
+	strip_prefix:   Option<String>,
+	strip_suffix:   Option<String>,
+}
+
+impl SortKeys {
+	// Makes sure there's a key for each item, dropping all of them if the options
+	// changed, and the ones of the files that are gone if they pile up
+	fn refresh(
+		&mut self,
+		opts: KeyOpts,
+		items: &[File],
+		key: impl Fn(&File) -> Box<[u8]>,
+	) -> Vec<&[u8]> {
+		if self.opts.as_ref() != Some(&opts) {
+			self.keys.clear();
+			self.opts = Some(opts);
+		} else if self.keys.len() > items.len() * 2 {
+			let live: HashSet<_> = items.iter().map(|f| f.urn()).collect();
+			self.keys.retain(|k, _| live.contains(k.as_urn()));
+		}
+
+		for f in items {
+			if !self.keys.contains_key(f.urn()) {
+				self.keys.insert(f.urn_owned(), key(f));
 			}
+		}
+		items.iter().map(|f| &*self.keys[f.urn()]).collect()
+	}
+}
+
+// A sorter with its toggles resolved for one call, which shadow the sorter's
+// own fields, while the rest are reached through `Deref`
+struct Sorting<'a> {
+	sorter:    &'a FilesSorter,
+	sensitive: bool,
+	reverse:   bool,
+	dir_first: bool,
+	translit:  bool,
+	stable:    bool,
+}
+
+impl Deref for Sorting<'_> {
+	type Target = FilesSorter;
+
+	#[inline]
+	fn deref(&self) -> &Self::Target { self.sorter }
+}
+
+impl Sorting<'_> {
+	fn sort_at(
+		&self,
+		items: &mut Vec<File>,
+		meta: Meta,
+		keys: Option<&mut SortKeys>,
+		now: SystemTime,
+	) {
+		if items.is_empty() {
+			return;
+		}
+
+		let counts;
+		let meta = if self.by == SortBy::ExtensionFrequency
+			|| self.tie_breaks.iter().any(|t| t.by == SortBy::ExtensionFrequency)
+		{
+			counts = items.iter().fold(HashMap::new(), |mut map, f| {
+				*map.entry(self.ext_group(f)).or_default() += 1;
+				map
+			});
+			Meta { ext_counts: Some(&counts), ..meta }
+		} else {
+			meta
+		};
+
+		let cutoff = self.recent_window.and_then(|w| now.checked_sub(w));
+		let cmp = |a: &File, b: &File| match self.by {
+			SortBy::Natural => self.cmp_natural(a, b, meta),
+			_ => self.cmp_by(a, b, meta),
+		};
+
+		let cached = match (keys, self.key_opts()) {
+			(Some(keys), Some(opts)) => Some(keys.refresh(opts, items, |f| self.cached_key(f))),
+			_ => None,
 		};
 
+		// Compare the cached keys, only falling back to the full comparison on a tie
+		if let Some(keys) = cached {
+			self.sort_indexed(items, |a, b, i, j| {
+				self.promote(a, b, cutoff).then_with(|| match keys[i].cmp(keys[j]) {
+					Ordering::Equal => cmp(a, b),
+					ord => self.directed(ord),
+				})
+			});
+			return;
+		}
+
 		match self.by {
 			SortBy::None => {}
-			SortBy::Mtime => items.sort_unstable_by(|a, b| {
-				let ord = self.cmp(a.mtime, b.mtime, self.promote(a, b));
-				if ord == Ordering::Equal { by_alphabetical(a, b) } else { ord }
-			}),
-			SortBy::Btime => items.sort_unstable_by(|a, b| {
-				let ord = self.cmp(a.btime, b.btime, self.promote(a, b));
-				if ord == Ordering::Equal { by_alphabetical(a, b) } else { ord }
-			}),
-			SortBy::Extension => items.sort_unstable_by(|a, b| {
-				let ord = if self.sensitive {
-					self.cmp(a.url.extension(), b.url.extension(), self.promote(a, b))
+			SortBy::Random => self.shuffle(items, cutoff),
+			SortBy::Natural => {
+				self.sort_indexed(items, |a, b, _, _| self.promote(a, b, cutoff).then_with(|| cmp(a, b)))
+			}
+			_ => self.sort_by(items, |a, b| self.promote(a, b, cutoff).then_with(|| cmp(a, b))),
+		}
+	}
+
+	// Compares by the sort key, then by the tie-breaks
+	fn cmp_by(&self, a: &File, b: &File, meta: Meta) -> Ordering {
+		let ord = self.directed(self.by_key(self.by, a, b, meta));
+		if ord != Ordering::Equal {
+			return ord;
+		}
+
+		let ord = self.tie_break(a, b, meta);
+		if ord != Ordering::Equal {
+			return ord;
+		}
+		// Files grouped by their parent read better in natural order
+		let by = if self.by == SortBy::Parent { SortBy::Natural } else { SortBy::Alphabetical };
+		self.directed(self.by_key(by, a, b, meta))
+	}
+
+	fn cmp_natural(&self, a: &File, b: &File, meta: Meta) -> Ordering {
+		let ord = self.by_natural(a, b);
+		if ord != Ordering::Equal {
+			return self.directed(ord);
+		}
+
+		let ord = self.tie_break(a, b, meta);
+		if ord != Ordering::Equal || self.stable {
+			return ord;
+		}
+		// Names that only differ in what transliteration or case folding erases
+		// would tie otherwise, so fall back to their bytes for a deterministic order,
+		// unless a stable sort is asked to keep them as they are
+		self.directed(a.name().cmp(b.name()))
+	}
+
+	// Compares by the tie-breaks in order, `Equal` if all of them tie
+	fn tie_break(&self, a: &File, b: &File, meta: Meta) -> Ordering {
+		for t in &self.tie_breaks {
+			let ord = self.by_key(t.by, a, b, meta);
+			let ord = if t.reverse { ord.reverse() } else { ord };
+			if ord != Ordering::Equal {
+				return self.directed(ord);
+			}
+		}
+		Ordering::Equal
+	}
+
+	// Sorts the indices rather than the files, which are much larger to move around
+	fn sort_indexed(
+		&self,
+		items: &mut Vec<File>,
+		mut f: impl FnMut(&File, &File, usize, usize) -> Ordering,
+	) {
+		let mut indices: Vec<usize> = (0..items.len()).collect();
+		self.sort_by(&mut indices, |&i, &j| f(&items[i], &items[j], i, j));
+		*items = indices.into_iter().map(|i| mem::take(&mut items[i])).collect();
+	}
+
+	// The options the sort key of the current sort depends on, if it can be cached
+	fn key_opts(&self) -> Option<KeyOpts> {
+		let cacheable = match self.by {
+			SortBy::Alphabetical => !self.log_rotation,
+			SortBy::Natural => {
+				!self.signed_numbers && !self.digits_last && self.group_separator.is_none()
+			}
+			_ => false,
+		};
+		if !cacheable || self.dir_anchor || self.sidecars_adjacent || self.collation.is_some() {
+			return None;
+		}
+
+		Some(KeyOpts {
+			by:             self.by,
+			bytewise:       self.sensitive || (self.by == SortBy::Alphabetical && self.ascii_case),
+			translit:       self.by == SortBy::Natural && self.translit,
+			normalize:      self.normalize,
+			trim_invisible: self.trim_invisible,
+			strip_prefix:   self.strip_prefix.clone(),
+			strip_suffix:   self.strip_suffix.clone(),
+		})
+	}
+
+	// A key whose byte order is the order of the names under `key_opts()`
+	fn cached_key(&self, f: &File) -> Box<[u8]> {
+		let key = self.key(f);
+		if self.by == SortBy::Natural {
+			natural_key(key.as_encoded_bytes(), self.translit, !self.sensitive).into()
+		} else if self.sensitive || self.ascii_case {
+			key.as_encoded_bytes().into()
+		} else {
+			key.to_ascii_uppercase().into_encoded_bytes().into()
+		}
+	}
+
+	// Fisher-Yates, then group by `promote` keeping the shuffled order in groups
+	fn shuffle(&self, items: &mut [File], cutoff: Option<SystemTime>) {
+		let mut rng = if self.shuffle_seeded {
+			let mut h = DefaultHasher::new();
+			items[0].url.parent_url().hash(&mut h);
+			items.len().hash(&mut h);
+			LcgRng::with_seed(h.finish())
+		} else {
+			LcgRng::default()
+		};
+
+		for i in (1..items.len()).rev() {
+			// The low bits of an LCG are far from random
+			let j = (rng.next().unwrap_or(0) >> 32) as usize % (i + 1);
+			items.swap(i, j);
+		}
+
+		// A single pass if nothing is promoted, as all compare equal
+		items.sort_by(|a, b| self.promote(a, b, cutoff));
+	}
+
+	// Compare by a single key in ascending order, without promotion or reversal
+	fn by_key(&self, by: SortBy, a: &File, b: &File, meta: Meta) -> Ordering {
+		let Meta {
+			known: SortMeta { sizes, visits, mimes, deep_mtimes, labels, manual, git, counts },
+			ext_counts,
+		} = meta;
+		match by {
+			SortBy::None | SortBy::Random => Ordering::Equal,
+			SortBy::Mtime => a.mtime.cmp(&b.mtime),
+			// Directories by the newest mtime anywhere inside them, once known
+			SortBy::DeepMtime => {
+				let mtime = |f: &File| {
+					if f.is_dir() { known(deep_mtimes, f).copied().or(f.mtime) } else { f.mtime }
+				};
+				mtime(a).cmp(&mtime(b))
+			}
+			SortBy::Btime => self.btime(a).cmp(&self.btime(b)),
+			SortBy::Extension => {
+				if self.sensitive {
+					self.ext_key(a.url.extension()).cmp(&self.ext_key(b.url.extension()))
 				} else {
-					self.cmp(
-						a.url.extension().map(|s| s.to_ascii_lowercase()),
-						b.url.extension().map(|s| s.to_ascii_lowercase()),
-						self.promote(a, b),
-					)
+					self
+						.ext_key(a.url.extension().map(|s| s.to_ascii_lowercase()))
+						.cmp(&self.ext_key(b.url.extension().map(|s| s.to_ascii_lowercase())))
+				}
+			}
+			// The most common extensions first, then by extension, and naturally by name within one
+			SortBy::ExtensionFrequency => {
+				let (ea, eb) = (self.ext_group(a), self.ext_group(b));
+				let count = |e: &Option<OsString>| ext_counts.and_then(|m| m.get(e)).copied().unwrap_or(0);
+				count(&eb).cmp(&count(&ea)).then_with(|| ea.cmp(&eb)).then_with(|| self.by_natural(a, b))
+			}
+			SortBy::Alphabetical => {
+				let ord = if self.log_rotation {
+					let (ka, kb) = (self.key(a), self.key(b));
+					let ((aa, na), (bb, nb)) = (Self::split_rotation(&ka), Self::split_rotation(&kb));
+					self.alphabetical(aa, bb).then(na.cmp(&nb))
+				} else {
+					self.alphabetical(&self.key(a), &self.key(b))
 				};
-				if ord == Ordering::Equal { by_alphabetical(a, b) } else { ord }
-			}),
-			SortBy::Alphabetical => items.sort_unstable_by(by_alphabetical),
-			SortBy::Natural => self.sort_naturally(items),
-			SortBy::Size => items.sort_unstable_by(|a, b| {
-				let aa = if a.is_dir() { sizes.get(a.urn()).copied() } else { None };
-				let bb = if b.is_dir() { sizes.get(b.urn()).copied() } else { None };
-				let ord = self.cmp(aa.unwrap_or(a.len), bb.unwrap_or(b.len), self.promote(a, b));
-				if ord == Ordering::Equal { by_alphabetical(a, b) } else { ord }
-			}),
-			SortBy::Random => {
-				let mut rng = LcgRng::default();
-				items.sort_unstable_by(|a, b| self.cmp(rng.next(), rng.next(), self.promote(a, b)))
+				if ord == Ordering::Equal && (self.dir_anchor || self.sidecars_adjacent) {
+					self.sidecars_last(a, b).then_with(|| self.alphabetical(a.name(), b.name()))
+				} else {
+					ord
+				}
+			}
+			SortBy::Natural => self.by_natural(a, b),
+			SortBy::Size => self.size(a, sizes).cmp(&self.size(b, sizes)),
+			SortBy::SizeBucket => {
+				let (aa, bb) = (self.size(a, sizes), self.size(b, sizes));
+				(Self::size_bucket(aa), aa).cmp(&(Self::size_bucket(bb), bb))
+			}
+			SortBy::LinkTarget => {
+				let aa = a.link_to.as_ref().unwrap_or(&a.url);
+				let bb = b.link_to.as_ref().unwrap_or(&b.url);
+				aa.as_os_str().cmp(bb.as_os_str())
+			}
+			// Most recently read or written first
+			SortBy::Recent => b.atime.max(b.mtime).cmp(&a.atime.max(a.mtime)),
+			// Last visited first, unvisited ones last
+			SortBy::Recency => known(visits, b).cmp(&known(visits, a)),
+			SortBy::Chronological => Self::created(a).cmp(&Self::created(b)),
+			// Files whose MIME type isn't known yet last
+			SortBy::Mime => {
+				let mime = |f: &File| known(mimes, f).map_or((true, ""), |m| (false, m.as_str()));
+				mime(a).cmp(&mime(b))
+			}
+			// By the position given to each file, the ones without one last
+			SortBy::Manual => {
+				let pos = |f: &File| known(manual, f).map_or((true, 0), |&i| (false, i));
+				pos(a).cmp(&pos(b))
+			}
+			// Changed files first, the most severe changes before the rest, clean ones last
+			SortBy::Git => {
+				let rank = |f: &File| known(git, f).map_or((true, None), |&c| (false, Some(c)));
+				rank(a).cmp(&rank(b))
+			}
+			// Directories with the most entries first, the ones not counted yet after them,
+			// then files, which are left to the tie-breaks
+			SortBy::ChildCount => {
+				let count =
+					|f: &File| (!f.is_dir(), Reverse(f.is_dir().then(|| known(counts, f)).flatten()));
+				count(a).cmp(&count(b))
+			}
+			// Labeled files first in the order of their colors, unlabeled ones last
+			SortBy::Label => {
+				let rank =
+					|f: &File| known(labels, f).copied().flatten().map_or((true, None), |l| (false, Some(l)));
+				rank(a).cmp(&rank(b))
+			}
+			SortBy::Parent => self.by_parent(a, b),
+			SortBy::Root => {
+				let ((ra, aa), (rb, bb)) = (Self::split_root(a), Self::split_root(b));
+				self.alphabetical(ra, rb).then_with(|| self.natural(aa.as_os_str(), bb.as_os_str()))
 			}
 		}
 	}
 
-	fn sort_naturally(&self, items: &mut Vec<File>) {
-		let mut indices: Vec<usize> = (0..items.len()).collect();
-		indices.sort_unstable_by(|&a, &b| {
-			let (a, b) = (&items[a], &items[b]);
+	#[inline]
+	fn by_natural(&self, a: &File, b: &File) -> Ordering {
+		let ord = self.natural(&self.key(a), &self.key(b));
+		if ord == Ordering::Equal && (self.dir_anchor || self.sidecars_adjacent) {
+			self.sidecars_last(a, b).then_with(|| self.natural(a.name(), b.name()))
+		} else {
+			ord
+		}
+	}
+
+	#[inline]
+	fn size(&self, f: &File, sizes: Option<&HashMap<UrnBuf, u64>>) -> u64 {
+		let own = || match self.size_mode {
+			SizeMode::Apparent => f.len,
+			SizeMode::Allocated => f.allocated(),
+		};
+
+		if !f.is_dir() {
+			own()
+		} else if let Some(&len) = known(sizes, f) {
+			len
+		} else if self.dir_unknown_zero {
+			0
+		} else {
+			own()
+		}
+	}
+
+	fn by_parent(&self, a: &File, b: &File) -> Ordering {
+		let (aa, bb) = (a.url.parent().unwrap_or(&a.url), b.url.parent().unwrap_or(&b.url));
+		self.natural(aa.as_os_str(), bb.as_os_str())
+	}
 
-			let promote = self.promote(a, b);
-			if promote != Ordering::Equal {
-				return promote;
+	// The extension files are grouped by, lowercased unless case-sensitive
+	fn ext_group(&self, f: &File) -> Option<OsString> {
+		let ext = f.url.extension()?;
+		Some(if self.sensitive { ext.to_owned() } else { ext.to_ascii_lowercase() })
+	}
+
+	// Splits off the final extension if it's a number, e.g. "app.log.10" into
+	// ("app.log", Some(10))
+	fn split_rotation(s: &OsStr) -> (&OsStr, Option<u64>) {
+		let p = Path::new(s);
+		match (p.file_stem(), p.extension().and_then(|e| e.to_str())) {
+			(Some(stem), Some(ext)) if ext.bytes().all(|b| b.is_ascii_digit()) => {
+				ext.parse().map_or((s, None), |n| (stem, Some(n)))
 			}
+			_ => (s, None),
+		}
+	}
 
-			let ordering = if self.translit {
-				natsort(
-					a.name().as_encoded_bytes().transliterate().as_bytes(),
-					b.name().as_encoded_bytes().transliterate().as_bytes(),
-					!self.sensitive,
-				)
-			} else {
-				natsort(a.name().as_encoded_bytes(), b.name().as_encoded_bytes(), !self.sensitive)
+	// The top-level directory of a file, and the rest of its path under it
+	#[inline]
+	fn split_root(f: &File) -> (&OsStr, &Path) {
+		let mut it = f.url.components();
+		let root = it.find_map(|c| match c {
+			Component::Normal(s) => Some(s),
+			_ => None,
+		});
+		(root.unwrap_or_default(), it.as_path())
+	}
+
+	// The part of the name used for comparison
+	#[inline]
+	fn key<'a>(&self, f: &'a File) -> Cow<'a, OsStr> {
+		let s = if self.dir_anchor && !self.dir_first && !f.is_dir() {
+			f.stem().unwrap_or(f.name())
+		} else if self.sidecars_adjacent && !f.is_dir() {
+			self.sidecar_base(f)
+		} else {
+			f.name()
+		};
+
+		let Some(mut s) = s.to_str().map(Cow::Borrowed) else {
+			return Cow::Borrowed(s);
+		};
+		if self.normalize && !is_nfc(&s) {
+			s = Cow::Owned(s.nfc().collect());
+		}
+		if self.trim_invisible {
+			s = Self::trim_invisible(s);
+		}
+		if self.strip_prefix.is_some() || self.strip_suffix.is_some() {
+			s = self.strip_affixes(s);
+		}
+
+		match s {
+			Cow::Borrowed(s) => Cow::Borrowed(OsStr::new(s)),
+			Cow::Owned(s) => Cow::Owned(s.into()),
+		}
+	}
+
+	// The name a file shares with its sidecars, e.g. "IMG_001" for "IMG_001.CR2",
+	// "IMG_001.CR2.xmp" and "IMG_001.xmp"
+	fn sidecar_base<'a>(&self, f: &'a File) -> &'a OsStr {
+		let Some(name) = f.name().to_str() else { return f.name() };
+		let primary = self.sidecar_primary(name).unwrap_or(name);
+		Path::new(primary).file_stem().unwrap_or(OsStr::new(primary))
+	}
+
+	// The name of a sidecar without its sidecar suffix, `None` if it isn't one
+	fn sidecar_primary<'a>(&self, name: &'a str) -> Option<&'a str> {
+		let strip = |suffix: &str| {
+			let n = name.len().checked_sub(suffix.len()).filter(|&n| n > 0)?;
+			name.get(..n).filter(|_| name.as_bytes()[n..].eq_ignore_ascii_case(suffix.as_bytes()))
+		};
+
+		match &self.sidecar_suffixes {
+			Some(suffixes) => suffixes.iter().find_map(|s| strip(s)),
+			None => FilesSorter::SIDECAR_SUFFIXES.iter().find_map(|s| strip(s)),
+		}
+	}
+
+	#[inline]
+	fn sidecars_last(&self, a: &File, b: &File) -> Ordering {
+		if !self.sidecars_adjacent {
+			return Ordering::Equal;
+		}
+		let sidecar = |f: &File| f.name().to_str().and_then(|s| self.sidecar_primary(s)).is_some();
+		sidecar(a).cmp(&sidecar(b))
+	}
+
+	// Removes `strip_prefix` and `strip_suffix`, as long as something is left
+	fn strip_affixes<'a>(&self, s: Cow<'a, str>) -> Cow<'a, str> {
+		let eq = |a: Option<&str>, b: &str| {
+			a.is_some_and(|a| if self.sensitive { a == b } else { a.eq_ignore_ascii_case(b) })
+		};
+
+		let (mut start, mut end) = (0, s.len());
+		if let Some(p) = self.strip_prefix.as_deref().filter(|p| eq(s.get(..p.len()), p)) {
+			start = p.len();
+		}
+		if let Some(p) =
+			self.strip_suffix.as_deref().filter(|p| eq(s.get(end.saturating_sub(p.len())..), p))
+		{
+			end -= p.len();
+		}
+
+		if start >= end || (start == 0 && end == s.len()) {
+			return s;
+		}
+		match s {
+			Cow::Borrowed(s) => Cow::Borrowed(&s[start..end]),
+			Cow::Owned(s) => Cow::Owned(s[start..end].to_owned()),
+		}
+	}
+
+	// Drops zero-width code points and trailing whitespace
+	fn trim_invisible(s: Cow<str>) -> Cow<str> {
+		const ZERO_WIDTH: [char; 5] = ['\u{200b}', '\u{200c}', '\u{200d}', '\u{2060}', '\u{feff}'];
+
+		let s = if s.contains(ZERO_WIDTH) { Cow::Owned(s.replace(ZERO_WIDTH, "")) } else { s };
+		match s {
+			Cow::Borrowed(s) => Cow::Borrowed(s.trim_end()),
+			Cow::Owned(s) => Cow::Owned(s.trim_end().to_owned()),
+		}
+	}
+
+	#[inline]
+	fn alphabetical(&self, a: &OsStr, b: &OsStr) -> Ordering {
+		if let (Some(table), Some(a), Some(b)) = (&self.collation, a.to_str(), b.to_str()) {
+			self.collated(table, a, b, false)
+		} else if self.sensitive || self.ascii_case {
+			a.cmp(b)
+		} else {
+			a.to_ascii_uppercase().cmp(&b.to_ascii_uppercase())
+		}
+	}
+
+	#[inline]
+	fn natural(&self, a: &OsStr, b: &OsStr) -> Ordering {
+		if let (Some(table), Some(a), Some(b)) = (&self.collation, a.to_str(), b.to_str()) {
+			return self.collated(table, a, b, true);
+		}
+
+		let opts = NatsortOpts {
+			insensitive: !self.sensitive,
+			signed_numbers: self.signed_numbers,
+			digits_last: self.digits_last,
+			group_separator: self.group_separator,
+			..Default::default()
+		};
+		if self.translit {
+			natsort_with(
+				a.as_encoded_bytes().transliterate().as_bytes(),
+				b.as_encoded_bytes().transliterate().as_bytes(),
+				opts,
+			)
+		} else {
+			natsort_with(a.as_encoded_bytes(), b.as_encoded_bytes(), opts)
+		}
+	}
+
+	// Compares by the position of each character in `table`, followed by those
+	// not in it in Unicode order, and with `natural` runs of digits by value
+	fn collated(&self, table: &[char], mut a: &str, mut b: &str, natural: bool) -> Ordering {
+		let fold = |c: char| if self.sensitive { c } else { c.to_lowercase().next().unwrap_or(c) };
+		let rank = |c: char| {
+			let c = fold(c);
+			table.iter().position(|&t| fold(t) == c).map_or((1, c as u32), |i| (0, i as u32))
+		};
+		let digits = |s: &str| s.bytes().take_while(u8::is_ascii_digit).count();
+
+		loop {
+			let (Some(x), Some(y)) = (a.chars().next(), b.chars().next()) else {
+				return (!a.is_empty()).cmp(&!b.is_empty());
 			};
 
-			if self.reverse { ordering.reverse() } else { ordering }
+			if natural && x.is_ascii_digit() && y.is_ascii_digit() {
+				let (na, nb) = (digits(a), digits(b));
+				let (da, db) = (a[..na].trim_start_matches('0'), b[..nb].trim_start_matches('0'));
+				let ord = da.len().cmp(&db.len()).then_with(|| da.cmp(db));
+				if ord != Ordering::Equal {
+					return ord;
+				}
+				(a, b) = (&a[na..], &b[nb..]);
+			} else {
+				let ord = rank(x).cmp(&rank(y));
+				if ord != Ordering::Equal {
+					return ord;
+				}
+				(a, b) = (&a[x.len_utf8()..], &b[y.len_utf8()..]);
+			}
+		}
+	}
+
+	#[inline]
+	fn ext_key<T: AsRef<OsStr>>(&self, ext: Option<T>) -> (usize, Option<T>) {
+		let rank = ext.as_ref().and_then(|e| {
+			let e = e.as_ref();
+			self
+				.ext_priority
+				.iter()
+				.position(|p| if self.sensitive { e == p.as_str() } else { e.eq_ignore_ascii_case(p) })
 		});
+		(rank.unwrap_or(self.ext_priority.len()), ext)
+	}
 
-		*items = indices.into_iter().map(|i| mem::take(&mut items[i])).collect();
+	#[inline]
+	fn btime(&self, f: &File) -> Option<SystemTime> {
+		let btime = f.btime.filter(|&t| t != UNIX_EPOCH);
+		if self.btime_fallback { btime.or(f.mtime) } else { f.btime }
 	}
 
-	#[inline(always)]
-	#[allow(clippy::collapsible_else_if)]
-	fn cmp<T: Ord>(&self, a: T, b: T, promote: Ordering) -> Ordering {
-		if promote != Ordering::Equal {
-			promote
-		} else {
-			if self.reverse { b.cmp(&a) } else { a.cmp(&b) }
+	// When the file came to be, the btime if known, otherwise the mtime
+	#[inline]
+	fn created(f: &File) -> Option<SystemTime> { f.btime.filter(|&t| t != UNIX_EPOCH).or(f.mtime) }
+
+	// Tiny (< 1 KiB), small (< 1 MiB), medium (< 1 GiB), and large
+	#[inline]
+	fn size_bucket(len: u64) -> u8 {
+		match len {
+			0..=1023 => 0,
+			1024..=1048575 => 1,
+			1048576..=1073741823 => 2,
+			_ => 3,
 		}
 	}
 
+	fn is_temp(&self, f: &File) -> bool {
+		let name = f.name().as_encoded_bytes();
+		let matches = |pat: &str| match pat.split_once('*') {
+			Some((prefix, suffix)) => {
+				name.len() >= prefix.len() + suffix.len()
+					&& name[..prefix.len()].eq_ignore_ascii_case(prefix.as_bytes())
+					&& name[name.len() - suffix.len()..].eq_ignore_ascii_case(suffix.as_bytes())
+			}
+			None => name.eq_ignore_ascii_case(pat.as_bytes()),
+		};
+
+		match &self.temp_patterns {
+			Some(patterns) => patterns.iter().any(|p| matches(p)),
+			None => FilesSorter::TEMP_PATTERNS.iter().any(|p| matches(p)),
+		}
+	}
+
+	#[inline]
+	fn sort_by<T>(&self, v: &mut [T], f: impl FnMut(&T, &T) -> Ordering) {
+		if self.stable { v.sort_by(f) } else { v.sort_unstable_by(f) }
+	}
+
+	// Applies `reverse` to the ordering of the data keys, which `promote` never
+	// goes through, so directories stay on top either way
+	#[inline(always)]
+	fn directed(&self, ord: Ordering) -> Ordering { if self.reverse { ord.reverse() } else { ord } }
+
+	// Layers of grouping that take precedence over the sort itself, in order:
+	// directories first, temporary files last, then the files created after
+	// `cutoff`, and the ones modified after `session_start`, newest first
 	#[inline(always)]
-	fn promote(&self, a: &File, b: &File) -> Ordering {
-		if self.dir_first { b.is_dir().cmp(&a.is_dir()) } else { Ordering::Equal }
+	fn promote(&self, a: &File, b: &File, cutoff: Option<SystemTime>) -> Ordering {
+		let first = |set: &HashSet<UrnBuf>| {
+			if set.is_empty() {
+				Ordering::Equal
+			} else {
+				set.contains(b.urn()).cmp(&set.contains(a.urn()))
+			}
+		};
+
+		let mut ord = first(&self.pinned).then_with(|| first(&self.match_set));
+
+		if self.dir_first {
+			ord = ord.then_with(|| b.is_dir().cmp(&a.is_dir()));
+		}
+
+		// Only files if directories are set apart, else everything, to be transitive
+		if self.files_by_parent && !(self.dir_first && (a.is_dir() || b.is_dir())) {
+			ord = ord.then_with(|| self.by_parent(a, b));
+		}
+
+		if self.broken_links_first {
+			ord = ord.then_with(|| b.is_broken_link().cmp(&a.is_broken_link()));
+		}
+
+		if self.temp_last {
+			ord = ord.then_with(|| self.is_temp(a).cmp(&self.is_temp(b)));
+		}
+
+		if let Some(cutoff) = cutoff {
+			ord = ord.then_with(|| {
+				let created = |f: &File| Self::created(f).filter(|&t| t > cutoff);
+				created(b).cmp(&created(a))
+			});
+		}
+
+		if let Some(start) = self.session_start {
+			ord = ord.then_with(|| {
+				let modified = |f: &File| f.mtime.filter(|&t| t > start);
+				modified(b).cmp(&modified(a))
+			});
+		}
+		ord
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use yazi_shared::fs::{Cha, ChaKind, Label, Url};
+
+	use super::*;
+
+	fn file(path: &str) -> File { File { url: Url::from(path), ..Default::default() } }
+
+	fn link(path: &str, to: &str, broken: bool) -> File {
+		let kind = if broken { ChaKind::ORPHAN } else { ChaKind::LINK };
+		File {
+			url: Url::from(path),
+			cha: Cha { kind, ..Default::default() },
+			link_to: Some(Url::from(to)),
+			..Default::default()
+		}
+	}
+
+	fn dir(path: &str) -> File {
+		File { cha: Cha { kind: ChaKind::DIR, ..Default::default() }, ..file(path) }
+	}
+
+	fn sized(path: &str, len: u64) -> File {
+		File { cha: Cha { len, ..Default::default() }, ..file(path) }
+	}
+
+	// With the birth and modification times in seconds since the epoch
+	fn timed(path: &str, btime: Option<u64>, mtime: Option<u64>) -> File {
+		let t = |s| UNIX_EPOCH + Duration::from_secs(s);
+		File {
+			cha: Cha { btime: btime.map(t), mtime: mtime.map(t), ..Default::default() },
+			..file(path)
+		}
+	}
+
+	fn names(items: &[File]) -> Vec<&str> {
+		items.iter().map(|f| f.name().to_str().unwrap()).collect()
+	}
+
+	#[test]
+	fn test_sort_link_target() {
+		let mut items = vec![
+			link("/w/a", "/z/target", false),
+			file("/w/c"),
+			link("/w/b", "/a/target", false),
+			link("/w/d", "/m/gone", true),
+			link("/w/e", "/w/c", false),
+		];
+
+		let sorter = FilesSorter { by: SortBy::LinkTarget, ..Default::default() };
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["b", "d", "c", "e", "a"]);
+	}
+
+	#[test]
+	fn test_sort_size_bucket() {
+		let mut items = vec![
+			sized("/g", 1 << 30),
+			sized("/f", (1 << 30) - 1),
+			sized("/e", 1 << 20),
+			dir("/d"),
+			sized("/c", 1023),
+			sized("/b", 1024),
+			sized("/a", 0),
+			sized("/h", 1023),
+		];
+		let sizes = [(UrnBuf::from("d"), 5 << 20)].into_iter().collect();
+
+		let mut sorter = FilesSorter { by: SortBy::SizeBucket, ..Default::default() };
+		sorter.sort(&mut items, &SortMeta { sizes: Some(&sizes), ..Default::default() });
+		assert_eq!(names(&items), ["a", "c", "h", "b", "e", "d", "f", "g"]);
+
+		sorter.reverse = true;
+		sorter.sort(&mut items, &SortMeta { sizes: Some(&sizes), ..Default::default() });
+		assert_eq!(names(&items), ["g", "f", "d", "e", "b", "h", "c", "a"]);
+	}
+
+	#[test]
+	fn test_sort_btime_fallback() {
+		let mut items = vec![
+			timed("/a", None, Some(300)),
+			timed("/b", Some(200), Some(400)),
+			timed("/c", Some(0), Some(100)),
+			timed("/d", None, None),
+		];
+
+		let mut sorter = FilesSorter { by: SortBy::Btime, ..Default::default() };
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["a", "d", "c", "b"]);
+
+		sorter.btime_fallback = true;
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["d", "c", "b", "a"]);
+	}
+
+	#[test]
+	fn test_sort_ext_priority() {
+		let mut items = vec![
+			file("/a.txt"),
+			file("/Cargo.TOML"),
+			file("/main.rs"),
+			file("/b.md"),
+			file("/lib.rs"),
+			dir("/src"),
+			file("/README"),
+		];
+
+		let mut sorter = FilesSorter {
+			by: SortBy::Extension,
+			dir_first: true,
+			ext_priority: vec!["rs".to_owned(), "toml".to_owned()],
+			..Default::default()
+		};
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), [
+			"src",
+			"lib.rs",
+			"main.rs",
+			"Cargo.TOML",
+			"README",
+			"b.md",
+			"a.txt"
+		]);
+
+		sorter.sensitive = true;
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), [
+			"src",
+			"lib.rs",
+			"main.rs",
+			"README",
+			"Cargo.TOML",
+			"b.md",
+			"a.txt"
+		]);
+	}
+
+	#[test]
+	fn test_sort_dir_anchor() {
+		for by in [SortBy::Alphabetical, SortBy::Natural] {
+			let mut items =
+				vec![file("/foo.txt"), file("/foo-bar"), dir("/foo"), file("/bar.rs"), dir("/bar")];
+
+			let mut sorter = FilesSorter { by, ..Default::default() };
+			sorter.sort(&mut items, &SortMeta::default());
+			assert_eq!(names(&items), ["bar", "bar.rs", "foo", "foo-bar", "foo.txt"], "{by}");
+
+			sorter.dir_anchor = true;
+			sorter.sort(&mut items, &SortMeta::default());
+			assert_eq!(names(&items), ["bar", "bar.rs", "foo", "foo.txt", "foo-bar"], "{by}");
+
+			sorter.dir_first = true;
+			sorter.sort(&mut items, &SortMeta::default());
+			assert_eq!(names(&items), ["bar", "foo", "bar.rs", "foo-bar", "foo.txt"], "{by}");
+		}
+	}
+
+	#[test]
+	fn test_sort_dir_unknown_zero() {
+		let mut items = vec![dir("/w/a"), dir("/w/b"), sized("/w/c", 100), sized("/w/d", 8192)];
+		// Directories report the size of their own entry, not of their contents
+		items[..2].iter_mut().for_each(|f| f.cha.len = 4096);
+		let sizes = HashMap::from_iter([(UrnBuf::from("b"), 10000)]);
+
+		let mut sorter = FilesSorter { by: SortBy::Size, ..Default::default() };
+		sorter.sort(&mut items, &SortMeta { sizes: Some(&sizes), ..Default::default() });
+		assert_eq!(names(&items), ["c", "a", "d", "b"]);
+
+		sorter.dir_unknown_zero = true;
+		sorter.sort(&mut items, &SortMeta { sizes: Some(&sizes), ..Default::default() });
+		assert_eq!(names(&items), ["a", "c", "d", "b"]);
+	}
+
+	#[test]
+	fn test_sort_normalize() {
+		// "é" decomposed (NFD) and composed (NFC)
+		let mut items = vec![file("/w/e\u{0301}a"), file("/w/\u{00e9}b")];
+
+		let mut sorter = FilesSorter { by: SortBy::Natural, translit: true, ..Default::default() };
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["\u{00e9}b", "e\u{0301}a"]);
+
+		sorter.normalize = true;
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["e\u{0301}a", "\u{00e9}b"]);
+
+		sorter.by = SortBy::Alphabetical;
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["e\u{0301}a", "\u{00e9}b"]);
+	}
+
+	#[test]
+	fn test_sort_recent() {
+		let accessed = |path: &str, atime: Option<u64>, mtime| {
+			let mut f = timed(path, None, mtime);
+			f.cha.atime = atime.map(|s| UNIX_EPOCH + Duration::from_secs(s));
+			f
+		};
+
+		let mut items = vec![
+			accessed("/w/a", Some(10), Some(10)),
+			accessed("/w/b", Some(50), Some(20)),
+			accessed("/w/c", Some(30), Some(40)),
+			accessed("/w/d", None, Some(45)),
+			accessed("/w/e", None, None),
+			accessed("/w/f", Some(40), Some(30)),
+		];
+
+		let mut sorter = FilesSorter { by: SortBy::Recent, ..Default::default() };
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["b", "d", "c", "f", "a", "e"]);
+
+		sorter.reverse = true;
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["e", "a", "f", "c", "d", "b"]);
+	}
+
+	#[test]
+	fn test_sort_root() {
+		let mut items = vec![
+			file("/srv/b/x10"),
+			file("/home/u/file"),
+			file("/srv/b/x9"),
+			file("/etc/hosts"),
+			file("/srv/a"),
+			file("/home/a/z"),
+		];
+
+		let sorter = FilesSorter { by: SortBy::Root, ..Default::default() };
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(items.iter().map(|f| f.url.to_str().unwrap()).collect::<Vec<_>>(), [
+			"/etc/hosts",
+			"/home/a/z",
+			"/home/u/file",
+			"/srv/a",
+			"/srv/b/x9",
+			"/srv/b/x10"
+		]);
+	}
+
+	#[test]
+	fn test_sort_trim_invisible() {
+		let mut sorter = FilesSorter { trim_invisible: true, ..Default::default() };
+		assert_eq!(
+			sorter.sorting(SortFlags::default()).key(&file("/w/a\u{200b}b \t")),
+			OsStr::new("ab")
+		);
+		assert_eq!(sorter.sorting(SortFlags::default()).key(&file("/w/\u{feff}a  ")), OsStr::new("a"));
+
+		let mut items =
+			vec![file("/w/a\u{200b}"), file("/w/a!"), file("/w/a1\u{200d}0"), file("/w/a9 ")];
+
+		sorter.by = SortBy::Alphabetical;
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["a\u{200b}", "a!", "a1\u{200d}0", "a9 "]);
+
+		sorter.by = SortBy::Natural;
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["a\u{200b}", "a!", "a9 ", "a1\u{200d}0"]);
+
+		sorter.trim_invisible = false;
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["a!", "a1\u{200d}0", "a9 ", "a\u{200b}"]);
+	}
+
+	#[test]
+	fn test_sort_reverse_promote() {
+		let items = vec![file("/w/b"), dir("/w/d"), file("/w/a"), dir("/w/c"), file("/w/e")];
+
+		for by in [SortBy::Alphabetical, SortBy::Natural, SortBy::Size] {
+			let mut sorter = FilesSorter { by, dir_first: true, reverse: true, ..Default::default() };
+			let mut v = items.clone();
+			sorter.sort(&mut v, &SortMeta::default());
+			assert_eq!(names(&v), ["d", "c", "e", "b", "a"], "{by}");
+
+			sorter.dir_first = false;
+			sorter.sort(&mut v, &SortMeta::default());
+			assert_eq!(names(&v), ["e", "d", "c", "b", "a"], "{by}");
+		}
+	}
+
+	#[test]
+	fn test_sort_recency() {
+		let t = |s| UNIX_EPOCH + std::time::Duration::from_secs(s);
+		let mut items = vec![file("/w/a"), file("/w/b"), file("/w/c"), file("/w/d"), file("/w/e")];
+		let visits = HashMap::from_iter([(UrnBuf::from("d"), t(10)), (UrnBuf::from("b"), t(30))]);
+
+		let sorter = FilesSorter { by: SortBy::Recency, ..Default::default() };
+		sorter.sort(&mut items, &SortMeta { visits: Some(&visits), ..Default::default() });
+		assert_eq!(names(&items), ["b", "d", "a", "c", "e"]);
+	}
+
+	#[test]
+	fn test_sort_parent() {
+		let mut items = vec![
+			file("/p/src10/b"),
+			file("/p/src2/a10"),
+			file("/p/lib/z"),
+			file("/p/src2/a9"),
+			file("/p/src10/a"),
+			file("/p/lib/y"),
+		];
+
+		let mut sorter = FilesSorter { by: SortBy::Parent, ..Default::default() };
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(items.iter().map(|f| f.url.to_str().unwrap()).collect::<Vec<_>>(), [
+			"/p/lib/y",
+			"/p/lib/z",
+			"/p/src2/a9",
+			"/p/src2/a10",
+			"/p/src10/a",
+			"/p/src10/b"
+		]);
+
+		sorter.tie_breaks = vec![TieBreak { by: SortBy::Alphabetical, reverse: true }];
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(items.iter().map(|f| f.url.to_str().unwrap()).collect::<Vec<_>>(), [
+			"/p/lib/z",
+			"/p/lib/y",
+			"/p/src2/a9",
+			"/p/src2/a10",
+			"/p/src10/b",
+			"/p/src10/a"
+		]);
+	}
+
+	#[test]
+	fn test_sort_recent_window() {
+		let t = |s| UNIX_EPOCH + Duration::from_secs(s);
+		let mut items = vec![
+			timed("/w/a", Some(1000), Some(1000)),
+			timed("/w/b", Some(1400), Some(1400)),
+			timed("/w/c", Some(1401), Some(1401)),
+			timed("/w/d", None, Some(1900)),
+			timed("/w/e", Some(100), Some(1950)),
+		];
+		let sorter = FilesSorter {
+			by: SortBy::Alphabetical,
+			recent_window: Some(Duration::from_secs(600)),
+			..Default::default()
+		};
+		let meta = Meta { known: SortMeta::default(), ext_counts: None };
+
+		// The window is exclusive, "b" created exactly at its edge is not recent
+		sorter.sorting(SortFlags::default()).sort_at(&mut items, meta, None, t(2000));
+		assert_eq!(names(&items), ["d", "c", "a", "b", "e"]);
+
+		sorter.sorting(SortFlags::default()).sort_at(&mut items, meta, None, t(2001));
+		assert_eq!(names(&items), ["d", "a", "b", "c", "e"]);
+	}
+
+	#[test]
+	fn test_sort_shuffle_seeded() {
+		let files = |n: usize| (0..n).map(|i| file(&format!("/w/{i}"))).collect::<Vec<_>>();
+		let shuffled = |mut items: Vec<File>| {
+			let sorter = FilesSorter { by: SortBy::Random, shuffle_seeded: true, ..Default::default() };
+			sorter.sort(&mut items, &SortMeta::default());
+			names(&items).into_iter().map(ToOwned::to_owned).collect::<Vec<_>>()
+		};
+
+		let a = shuffled(files(20));
+		assert_eq!(a, shuffled(files(20)));
+		assert_ne!(a, names(&files(20)));
+
+		let mut b = shuffled(files(21));
+		b.retain(|s| s != "20");
+		assert_ne!(a, b);
+
+		let mut c = a.clone();
+		c.sort_unstable_by_key(|s| s.parse::<u8>().unwrap());
+		assert_eq!(c, names(&files(20)));
+	}
+
+	#[test]
+	fn test_sort_strip_affixes() {
+		let mut items = vec![
+			file("/b/snapshot-c-data.tar"),
+			file("/b/Snapshot-b.TAR"),
+			file("/b/b-data.tar"),
+			file("/b/snapshot-a.tar"),
+			file("/b/snapshot-.tar"),
+		];
+
+		let mut sorter = FilesSorter {
+			by: SortBy::Natural,
+			strip_prefix: Some("snapshot-".to_owned()),
+			strip_suffix: Some(".tar".to_owned()),
+			..Default::default()
+		};
+		sorter.sort(&mut items, &SortMeta::default());
+		// Nothing would be left of "snapshot-.tar", so it's compared as is
+		assert_eq!(names(&items), [
+			"snapshot-a.tar",
+			"Snapshot-b.TAR",
+			"b-data.tar",
+			"snapshot-c-data.tar",
+			"snapshot-.tar"
+		]);
+
+		sorter.by = SortBy::Alphabetical;
+		sorter.sensitive = true;
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), [
+			"Snapshot-b.TAR",
+			"snapshot-a.tar",
+			"b-data.tar",
+			"snapshot-c-data.tar",
+			"snapshot-.tar"
+		]);
+	}
+
+	#[test]
+	fn test_sort_chronological() {
+		let mut items = vec![
+			timed("/p/a.jpg", Some(30), Some(90)),
+			timed("/p/b.jpg", None, Some(50)),
+			timed("/p/c.jpg", Some(10), Some(100)),
+			timed("/p/d.jpg", Some(0), Some(20)),
+			timed("/p/e.jpg", None, Some(50)),
+		];
+
+		let mut sorter = FilesSorter { by: SortBy::Mtime, ..Default::default() };
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["d.jpg", "b.jpg", "e.jpg", "a.jpg", "c.jpg"]);
+
+		sorter.by = SortBy::Chronological;
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["c.jpg", "d.jpg", "a.jpg", "b.jpg", "e.jpg"]);
+	}
+
+	#[test]
+	fn test_is_deterministic() {
+		for by in [
+			SortBy::None,
+			SortBy::Mtime,
+			SortBy::Alphabetical,
+			SortBy::Natural,
+			SortBy::Size,
+			SortBy::Recency,
+			SortBy::Chronological,
+		] {
+			assert!(FilesSorter { by, ..Default::default() }.is_deterministic(), "{by}");
+		}
+
+		let mut sorter = FilesSorter { by: SortBy::Random, ..Default::default() };
+		assert!(!sorter.is_deterministic());
+		sorter.shuffle_seeded = true;
+		assert!(sorter.is_deterministic());
+
+		let sorter = FilesSorter { recent_window: Some(Duration::from_secs(60)), ..Default::default() };
+		assert!(!sorter.is_deterministic());
+	}
+
+	#[test]
+	fn test_sort_signed_numbers() {
+		let mut items = vec![file("/w/delta-5"), file("/w/delta2"), file("/w/delta-10")];
+
+		let mut sorter = FilesSorter { by: SortBy::Natural, ..Default::default() };
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["delta-5", "delta-10", "delta2"]);
+
+		sorter.signed_numbers = true;
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["delta-10", "delta-5", "delta2"]);
+	}
+
+	#[test]
+	fn test_sort_digits_last() {
+		let mut items = vec![file("/g/2nd"), file("/g/beta"), file("/g/10th"), file("/g/Alpha")];
+
+		let mut sorter = FilesSorter { by: SortBy::Natural, ..Default::default() };
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["2nd", "10th", "Alpha", "beta"]);
+
+		sorter.digits_last = true;
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["Alpha", "beta", "2nd", "10th"]);
+	}
+
+	#[test]
+	fn test_sort_ascii_case() {
+		let mut items = vec![file("/z/apple"), file("/z/Zebra"), file("/z/banana"), file("/z/Apple")];
+
+		let mut sorter = FilesSorter { by: SortBy::Alphabetical, ..Default::default() };
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items)[2..], ["banana", "Zebra"]);
+
+		sorter.ascii_case = true;
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["Apple", "Zebra", "apple", "banana"]);
+
+		sorter.reverse = true;
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["banana", "apple", "Zebra", "Apple"]);
+	}
+
+	#[test]
+	fn test_sort_natural_ties() {
+		let sorter = FilesSorter { by: SortBy::Natural, translit: true, ..Default::default() };
+		for names_in in [["café", "cafe", "Cafe"], ["Cafe", "café", "cafe"], ["cafe", "Cafe", "café"]]
+		{
+			let mut items: Vec<_> = names_in.iter().map(|n| file(&format!("/t/{n}"))).collect();
+			sorter.sort(&mut items, &SortMeta::default());
+			assert_eq!(names(&items), ["Cafe", "cafe", "café"]);
+		}
+	}
+
+	#[test]
+	fn test_sort_natural_accents_and_case() {
+		let mut items: Vec<_> = ["CAFE10", "café2", "Café1", "cafe3", "CAFÉ2", "résumé 9", "Resume 10"]
+			.map(|n| file(&format!("/t/{n}")))
+			.into();
+		let expected = ["Café1", "CAFÉ2", "café2", "cafe3", "CAFE10", "résumé 9", "Resume 10"];
+
+		let sorter = FilesSorter { by: SortBy::Natural, translit: true, ..Default::default() };
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), expected);
+
+		items.reverse();
+		sorter.sort_cached(&mut items, &SortMeta::default(), &mut SortKeys::default());
+		assert_eq!(names(&items), expected);
+
+		// Only case folding, so the accented ones sort after "z"
+		let sorter = FilesSorter { by: SortBy::Natural, ..Default::default() };
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), [
+			"cafe3",
+			"CAFE10",
+			"CAFÉ2",
+			"Café1",
+			"café2",
+			"Resume 10",
+			"résumé 9"
+		]);
+	}
+
+	#[test]
+	fn test_sort_log_rotation() {
+		let mut items: Vec<_> = [
+			"app.log.10",
+			"app.log.gz",
+			"app.log.2",
+			"app.log",
+			"app.log.2.gz",
+			"app.log.5",
+			"app.log.1",
+		]
+		.map(|n| file(&format!("/var/log/{n}")))
+		.into();
+
+		let mut sorter = FilesSorter { by: SortBy::Alphabetical, ..Default::default() };
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), [
+			"app.log",
+			"app.log.1",
+			"app.log.10",
+			"app.log.2",
+			"app.log.2.gz",
+			"app.log.5",
+			"app.log.gz"
+		]);
+
+		sorter.log_rotation = true;
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), [
+			"app.log",
+			"app.log.1",
+			"app.log.2",
+			"app.log.5",
+			"app.log.10",
+			"app.log.2.gz",
+			"app.log.gz"
+		]);
+	}
+
+	#[test]
+	fn test_sort_deep_mtime() {
+		let t = |secs| Some(UNIX_EPOCH + Duration::from_secs(secs));
+		let dir_at =
+			|path: &str, secs| File { cha: Cha { mtime: t(secs), ..dir(path).cha }, ..file(path) };
+
+		let mut items = vec![
+			dir_at("/p/old", 10),
+			timed("/p/notes", None, Some(40)),
+			dir_at("/p/new", 50),
+			dir_at("/p/idle", 30),
+		];
+		let deep = HashMap::from_iter([
+			(UrnBuf::from("old"), t(90).unwrap()),
+			(UrnBuf::from("new"), t(60).unwrap()),
+		]);
+
+		let mut sorter = FilesSorter { by: SortBy::Mtime, ..Default::default() };
+		sorter.sort(&mut items, &SortMeta { deep_mtimes: Some(&deep), ..Default::default() });
+		assert_eq!(names(&items), ["old", "idle", "notes", "new"]);
+
+		sorter.by = SortBy::DeepMtime;
+		sorter.sort(&mut items, &SortMeta { deep_mtimes: Some(&deep), ..Default::default() });
+		assert_eq!(names(&items), ["idle", "notes", "new", "old"]);
+	}
+
+	#[test]
+	fn test_sort_with() {
+		let mut items = vec![file("/w/a"), dir("/w/b"), file("/w/C"), dir("/w/d")];
+
+		let sorter = FilesSorter { by: SortBy::Alphabetical, dir_first: true, ..Default::default() };
+		let flags = SortFlags { dir_first: Some(false), sensitive: Some(true), ..Default::default() };
+		sorter.sort_with(&mut items, &SortMeta::default(), flags);
+		assert_eq!(names(&items), ["C", "a", "b", "d"]);
+
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["b", "d", "a", "C"]);
+		assert!(sorter.dir_first && !sorter.sensitive);
+	}
+
+	#[test]
+	fn test_temp_last() {
+		let sorter = FilesSorter { by: SortBy::Alphabetical, temp_last: true, ..Default::default() };
+		let sorting = sorter.sorting(SortFlags::default());
+		for name in ["a.part", "b.CRDOWNLOAD", "notes.txt~", "x.tmp", "#draft.md#"] {
+			assert!(sorting.is_temp(&file(&format!("/w/{name}"))), "{name}");
+		}
+		for name in ["notes.txt", "partial", "tmp", "#", "#draft.md"] {
+			assert!(!sorting.is_temp(&file(&format!("/w/{name}"))), "{name}");
+		}
+
+		let mut items = vec![file("/w/b.part"), file("/w/c.txt"), file("/w/a.txt~"), file("/w/d.txt")];
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["c.txt", "d.txt", "a.txt~", "b.part"]);
+
+		let sorter =
+			FilesSorter { temp_last: true, temp_patterns: Some(vec!["*.bak".to_owned()]), ..sorter };
+		let mut items = vec![file("/w/b.bak"), file("/w/c.part"), file("/w/a.txt")];
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["a.txt", "c.part", "b.bak"]);
+	}
+
+	#[test]
+	fn test_preset_media() {
+		let mut items = vec![
+			sized("/m/b.mp4", 300),
+			sized("/m/a.jpg", 10),
+			sized("/m/c.mp4", 900),
+			sized("/m/d.jpg", 50),
+			sized("/m/e.flac", 20),
+			sized("/m/f.jpg", 50),
+		];
+
+		FilesSorter::preset_media().sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["e.flac", "d.jpg", "f.jpg", "a.jpg", "c.mp4", "b.mp4"]);
+	}
+
+	#[test]
+	fn test_preset_cleanup() {
+		let mut items = vec![
+			sized("/m/a.png", 100),
+			dir("/m/e"),
+			sized("/m/v.mkv", 50),
+			sized("/m/x.bin", 999),
+			sized("/m/b.png", 900),
+			dir("/m/c"),
+			sized("/m/w.mp4", 700),
+		];
+
+		let sizes = HashMap::from_iter([(UrnBuf::from("c"), 5000), (UrnBuf::from("e"), 10)]);
+		let mimes = HashMap::from_iter(
+			[
+				("a.png", "image/png"),
+				("b.png", "image/png"),
+				("c", "inode/directory"),
+				("e", "inode/directory"),
+				("v.mkv", "video/x-matroska"),
+				("w.mp4", "video/mp4"),
+			]
+			.map(|(k, v)| (UrnBuf::from(k), v.to_owned())),
+		);
+
+		let sorter = FilesSorter::preset_cleanup();
+		assert!(sorter.uses_size());
+		assert!(sorter.uses(SortBy::Mime) && sorter.uses(SortBy::Size));
+		assert!(!sorter.uses(SortBy::Recency));
+		sorter.sort(&mut items, &SortMeta {
+			sizes: Some(&sizes),
+			mimes: Some(&mimes),
+			..Default::default()
+		});
+		assert_eq!(names(&items), ["b.png", "a.png", "c", "e", "w.mp4", "v.mkv", "x.bin"]);
+	}
+
+	#[test]
+	fn test_sort_stable() {
+		for by in [SortBy::Alphabetical, SortBy::Natural, SortBy::Extension, SortBy::Mtime] {
+			let sorter = FilesSorter { by, stable: true, ..Default::default() };
+
+			let mut items: Vec<_> = (0..50).map(|i| file(if i % 2 == 0 { "/a" } else { "/A" })).collect();
+			items.extend((0..50).map(|i| file(if i % 3 == 0 { "/B" } else { "/b" })));
+			items.iter_mut().enumerate().for_each(|(i, f)| f.cha.len = i as u64);
+
+			sorter.sort(&mut items, &SortMeta::default());
+			let lens: Vec<_> = items.iter().map(|f| f.len).collect();
+			assert_eq!(lens, (0..100).collect::<Vec<_>>(), "{by}");
+		}
+	}
+
+	#[test]
+	fn test_sort_natural_tie_breaks() {
+		let items = vec![
+			sized("/w/a", 1),
+			sized("/w/B", 3),
+			sized("/w/A", 2),
+			sized("/w/b", 4),
+			sized("/w/c", 9),
+		];
+
+		// Only the names tying when case is ignored go by the tie-break, with or
+		// without the cached keys
+		for stable in [false, true] {
+			let sorter = FilesSorter {
+				by: SortBy::Natural,
+				stable,
+				tie_breaks: vec![TieBreak { by: SortBy::Size, reverse: true }],
+				..Default::default()
+			};
+
+			let mut sorted = items.clone();
+			sorter.sort(&mut sorted, &SortMeta::default());
+			assert_eq!(names(&sorted), ["A", "a", "b", "B", "c"], "{stable}");
+
+			let mut sorted = items.clone();
+			sorter.sort_cached(&mut sorted, &SortMeta::default(), &mut SortKeys::default());
+			assert_eq!(names(&sorted), ["A", "a", "b", "B", "c"], "{stable}");
+		}
+	}
+
+	#[test]
+	fn test_sort_cached() {
+		let mut items = vec![
+			file("/w/file10.txt"),
+			file("/w/File2.txt"),
+			file("/w/file2.txt"),
+			dir("/w/b"),
+			file("/w/Éclair"),
+			file("/w/eclair"),
+			file("/w/_tmp"),
+			file("/w/a"),
+			dir("/w/A"),
+			file("/w/file02.txt"),
+		];
+
+		let sorters = [
+			FilesSorter { by: SortBy::Alphabetical, ..Default::default() },
+			FilesSorter {
+				by: SortBy::Alphabetical,
+				sensitive: true,
+				reverse: true,
+				..Default::default()
+			},
+			FilesSorter {
+				by: SortBy::Alphabetical,
+				ascii_case: true,
+				dir_first: true,
+				..Default::default()
+			},
+			FilesSorter { by: SortBy::Natural, ..Default::default() },
+			FilesSorter { by: SortBy::Natural, translit: true, ..Default::default() },
+			FilesSorter { by: SortBy::Natural, sensitive: true, dir_first: true, ..Default::default() },
+			FilesSorter {
+				by: SortBy::Natural,
+				strip_prefix: Some("file".to_owned()),
+				..Default::default()
+			},
+			FilesSorter { by: SortBy::Natural, stable: true, reverse: true, ..Default::default() },
+			FilesSorter { by: SortBy::Size, ..Default::default() },
+		];
+
+		// One cache across all of them, to make sure a change of options invalidates it
+		let mut keys = SortKeys::default();
+		for sorter in sorters.iter().chain(sorters.iter().rev()) {
+			items.reverse();
+			let mut uncached = items.clone();
+			sorter.sort(&mut uncached, &SortMeta::default());
+
+			sorter.sort_cached(&mut items, &SortMeta::default(), &mut keys);
+			assert_eq!(names(&items), names(&uncached), "{}", sorter.by);
+		}
+
+		// Files gone from the folder are pruned once they pile up
+		items.truncate(5);
+		sorters[0].sort_cached(&mut items, &SortMeta::default(), &mut keys);
+		assert_eq!(keys.keys.len(), 10);
+		items.truncate(1);
+		sorters[0].sort_cached(&mut items, &SortMeta::default(), &mut keys);
+		assert_eq!(keys.keys.len(), 1);
+	}
+
+	#[test]
+	fn test_broken_links_first() {
+		let mut items = vec![
+			file("/w/c"),
+			link("/w/e", "/w/c", false),
+			link("/w/d", "/gone", true),
+			dir("/w/f"),
+			file("/w/a"),
+			link("/w/b", "/gone", true),
+		];
+
+		let mut sorter =
+			FilesSorter { by: SortBy::Alphabetical, broken_links_first: true, ..Default::default() };
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["b", "d", "a", "c", "e", "f"]);
+
+		// Still first among files, but after directories
+		sorter.dir_first = true;
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["f", "b", "d", "a", "c", "e"]);
+
+		// Reversing the sort doesn't move them
+		sorter.reverse = true;
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["f", "d", "b", "e", "c", "a"]);
+	}
+
+	#[test]
+	fn test_pinned() {
+		let mut items =
+			vec![dir("/w/d2"), file("/w/f3"), dir("/w/d1"), file("/w/f1"), file("/w/f2"), dir("/w/d3")];
+
+		let pinned = ["f2", "d3", "f3"].into_iter().map(UrnBuf::from).collect();
+		let mut sorter =
+			FilesSorter { by: SortBy::Alphabetical, pinned: Arc::new(pinned), ..Default::default() };
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["d3", "f2", "f3", "d1", "d2", "f1"]);
+
+		// Pinned files go before unpinned directories
+		sorter.dir_first = true;
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["d3", "f2", "f3", "d1", "d2", "f1"]);
+
+		sorter.reverse = true;
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["d3", "f3", "f2", "d2", "d1", "f1"]);
+
+		sorter.pinned = Default::default();
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["d3", "d2", "d1", "f3", "f2", "f1"]);
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn test_size_mode() {
+		let allocated = |path: &str, len: u64, blocks: u64| File {
+			cha: Cha { len, blocks, ..Default::default() },
+			..file(path)
+		};
+		let mut items = vec![
+			allocated("/w/sparse.img", 1 << 30, 8),
+			allocated("/w/movie.mkv", 700 << 20, 700 << 11),
+			allocated("/w/notes.txt", 100, 8),
+			allocated("/w/empty", 0, 0),
+		];
+
+		let mut sorter = FilesSorter { by: SortBy::Size, ..Default::default() };
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["empty", "notes.txt", "movie.mkv", "sparse.img"]);
+
+		// A 4K block each for the sparse image and the small text file, which then tie
+		sorter.size_mode = SizeMode::Allocated;
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["empty", "notes.txt", "sparse.img", "movie.mkv"]);
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn test_size_mode_sparse_file() {
+		let dir = std::env::temp_dir().join(format!("yazi-sparse-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+		let cha = |name: &str, f: &dyn Fn(&std::fs::File)| {
+			let path = dir.join(name);
+			f(&std::fs::File::create(&path).unwrap());
+			File {
+				url: Url::from(&path),
+				cha: Cha::from(std::fs::metadata(&path).unwrap()),
+				..Default::default()
+			}
+		};
+
+		let mut items = vec![
+			cha("sparse", &|f| f.set_len(64 << 20).unwrap()),
+			cha("dense", &|mut f| std::io::Write::write_all(&mut f, &[1; 1 << 20]).unwrap()),
+		];
+		std::fs::remove_dir_all(&dir).ok();
+		assert!(items[0].allocated() < items[0].len);
+
+		let mut sorter = FilesSorter { by: SortBy::Size, ..Default::default() };
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["dense", "sparse"]);
+
+		sorter.size_mode = SizeMode::Allocated;
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["sparse", "dense"]);
+	}
+
+	#[test]
+	fn test_sort_extension_frequency() {
+		let mut items: Vec<_> = [
+			"/w/IMG_10.JPG",
+			"/w/notes.txt",
+			"/w/IMG_9.jpg",
+			"/w/b.pdf",
+			"/w/Makefile",
+			"/w/IMG_2.jpg",
+			"/w/a.txt",
+			"/w/c.pdf",
+			"/w/IMG_1.jpg",
+			"/w/LICENSE",
+			"/w/x.zip",
+		]
+		.into_iter()
+		.map(file)
+		.collect();
+
+		// 4 jpg, then 2 each without an extension, pdf and txt, by extension
+		let mut sorter = FilesSorter { by: SortBy::ExtensionFrequency, ..Default::default() };
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), [
+			"IMG_1.jpg",
+			"IMG_2.jpg",
+			"IMG_9.jpg",
+			"IMG_10.JPG",
+			"LICENSE",
+			"Makefile",
+			"b.pdf",
+			"c.pdf",
+			"a.txt",
+			"notes.txt",
+			"x.zip"
+		]);
+
+		// "JPG" is counted on its own
+		sorter.sensitive = true;
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items)[..3], ["IMG_1.jpg", "IMG_2.jpg", "IMG_9.jpg"]);
+		assert_eq!(names(&items)[9..], ["IMG_10.JPG", "x.zip"]);
+	}
+
+	#[test]
+	fn test_sort_manual() {
+		let mut items: Vec<_> =
+			["/w/e", "/w/b", "/w/d", "/w/a", "/w/c", "/w/f"].into_iter().map(file).collect();
+		let manual =
+			[("d", 0), ("a", 1), ("f", 7)].into_iter().map(|(s, i)| (UrnBuf::from(s), i)).collect();
+
+		// The arranged files in their order, gaps and all, then the rest alphabetically
+		let mut sorter = FilesSorter { by: SortBy::Manual, ..Default::default() };
+		sorter.sort(&mut items, &SortMeta { manual: Some(&manual), ..Default::default() });
+		assert_eq!(names(&items), ["d", "a", "f", "b", "c", "e"]);
+
+		sorter.dir_first = true;
+		items[4].cha.kind = ChaKind::DIR;
+		sorter.sort(&mut items, &SortMeta { manual: Some(&manual), ..Default::default() });
+		assert_eq!(names(&items), ["c", "d", "a", "f", "b", "e"]);
+
+		// Nothing arranged
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["c", "a", "b", "d", "e", "f"]);
+	}
+
+	#[test]
+	fn test_files_by_parent() {
+		let mut items = vec![
+			sized("/p/src10/b", 5),
+			dir("/p/src2/mod"),
+			sized("/p/src2/a10", 1),
+			sized("/p/lib/z", 2),
+			dir("/p/lib"),
+			sized("/p/src2/a9", 9),
+			sized("/p/src10/a", 3),
+			dir("/p/assets"),
+			sized("/p/lib/y", 8),
+		];
+		let paths =
+			|items: &[File]| items.iter().map(|f| f.url.to_str().unwrap().to_owned()).collect::<Vec<_>>();
+
+		// Directories first by size, then files by their directory and size in it
+		let mut sorter = FilesSorter {
+			by: SortBy::Size,
+			dir_first: true,
+			files_by_parent: true,
+			..Default::default()
+		};
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(paths(&items), [
+			"/p/assets",
+			"/p/lib",
+			"/p/src2/mod",
+			"/p/lib/z",
+			"/p/lib/y",
+			"/p/src2/a10",
+			"/p/src2/a9",
+			"/p/src10/a",
+			"/p/src10/b"
+		]);
+
+		// Without `dir_first`, directories are grouped by their parent as well
+		sorter.dir_first = false;
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(paths(&items), [
+			"/p/assets",
+			"/p/lib",
+			"/p/lib/z",
+			"/p/lib/y",
+			"/p/src2/mod",
+			"/p/src2/a10",
+			"/p/src2/a9",
+			"/p/src10/a",
+			"/p/src10/b"
+		]);
+	}
+
+	#[test]
+	fn test_match_set() {
+		let mut items = vec![
+			file("/w/report-2.pdf"),
+			dir("/w/reports"),
+			file("/w/notes.txt"),
+			dir("/w/archive"),
+			file("/w/report-10.pdf"),
+			file("/w/budget.xlsx"),
+		];
+
+		let matched =
+			["report-2.pdf", "reports", "report-10.pdf"].into_iter().map(UrnBuf::from).collect();
+		let mut sorter = FilesSorter {
+			by: SortBy::Natural,
+			dir_first: true,
+			match_set: Arc::new(matched),
+			..Default::default()
+		};
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), [
+			"reports",
+			"report-2.pdf",
+			"report-10.pdf",
+			"archive",
+			"budget.xlsx",
+			"notes.txt"
+		]);
+
+		// Pinned files still come before the matching ones
+		sorter.pinned = Arc::new(["notes.txt"].into_iter().map(UrnBuf::from).collect());
+		sorter.reverse = true;
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), [
+			"notes.txt",
+			"reports",
+			"report-10.pdf",
+			"report-2.pdf",
+			"archive",
+			"budget.xlsx"
+		]);
+	}
+
+	#[test]
+	fn test_sort_by_key_fn() {
+		let mut items =
+			vec![file("/w/ccc.rs"), dir("/w/docs"), file("/w/a.md"), file("/w/bb.rs"), file("/w/b.toml")];
+
+		// By the length of the name, ties broken naturally
+		let len = |f: &File| f.name().len() as i64;
+		let mut sorter = FilesSorter { dir_first: true, ..Default::default() };
+		sorter.sort_by_key_fn(&mut items, len);
+		assert_eq!(names(&items), ["docs", "a.md", "bb.rs", "b.toml", "ccc.rs"]);
+
+		sorter.reverse = true;
+		sorter.sort_by_key_fn(&mut items, len);
+		assert_eq!(names(&items), ["docs", "ccc.rs", "b.toml", "bb.rs", "a.md"]);
+
+		// By a priority of the extension, with the pinned files still on top
+		let priority = |f: &File| match f.url.extension().and_then(|e| e.to_str()) {
+			Some("rs") => -1,
+			Some("toml") => 1,
+			_ => 0,
+		};
+		let sorter = FilesSorter {
+			pinned: Arc::new(["a.md"].into_iter().map(UrnBuf::from).collect()),
+			..Default::default()
+		};
+		sorter.sort_by_key_fn(&mut items, priority);
+		assert_eq!(names(&items), ["a.md", "bb.rs", "ccc.rs", "docs", "b.toml"]);
+	}
+
+	#[test]
+	fn test_sort_preserving() {
+		let sorter = FilesSorter { by: SortBy::Natural, ..Default::default() };
+		let mut items = vec![file("/w/b"), file("/w/d"), file("/w/f")];
+		let focus = UrnBuf::from("d");
+		assert_eq!(sorter.sort_preserving(&mut items, &SortMeta::default(), &focus), Some(1));
+
+		// Added before it, and after it
+		items.extend([file("/w/a"), file("/w/c"), file("/w/e")]);
+		assert_eq!(sorter.sort_preserving(&mut items, &SortMeta::default(), &focus), Some(3));
+		assert_eq!(names(&items[2..5]), ["c", "d", "e"]);
+
+		// Removed before it
+		items.retain(|f| f.name() != "a" && f.name() != "b");
+		assert_eq!(sorter.sort_preserving(&mut items, &SortMeta::default(), &focus), Some(1));
+
+		// By size, with the directory sizes taken into account
+		let sorter = FilesSorter { by: SortBy::Size, ..Default::default() };
+		let mut items = vec![dir("/w/x"), dir("/w/y"), dir("/w/z")];
+		let sizes =
+			[("x", 30), ("y", 10), ("z", 20)].into_iter().map(|(k, v)| (UrnBuf::from(k), v)).collect();
+		let meta = SortMeta { sizes: Some(&sizes), ..Default::default() };
+		assert_eq!(sorter.sort_preserving(&mut items, &meta, &UrnBuf::from("x")), Some(2));
+
+		assert_eq!(sorter.sort_preserving(&mut items, &meta, &UrnBuf::from("gone")), None);
+	}
+
+	#[test]
+	fn test_sort_git() {
+		let mut items = vec![
+			file("/w/README.md"),
+			file("/w/main10.rs"),
+			dir("/w/src"),
+			file("/w/new.txt"),
+			file("/w/main2.rs"),
+			file("/w/Cargo.lock"),
+			file("/w/clean.rs"),
+		];
+
+		let git = [
+			("main10.rs", GitChange::Modified),
+			("new.txt", GitChange::Untracked),
+			("main2.rs", GitChange::Modified),
+			("Cargo.lock", GitChange::Conflicted),
+			("src", GitChange::Modified),
+		]
+		.into_iter()
+		.map(|(s, c)| (UrnBuf::from(s), c))
+		.collect();
+
+		let mut sorter = FilesSorter::preset_git();
+		sorter.sort(&mut items, &SortMeta { git: Some(&git), ..Default::default() });
+		assert_eq!(names(&items), [
+			"Cargo.lock",
+			"main2.rs",
+			"main10.rs",
+			"src",
+			"new.txt",
+			"clean.rs",
+			"README.md"
+		]);
+
+		// Directories still first, with the rest ranked as before
+		sorter.dir_first = true;
+		sorter.sort(&mut items, &SortMeta { git: Some(&git), ..Default::default() });
+		assert_eq!(names(&items), [
+			"src",
+			"Cargo.lock",
+			"main2.rs",
+			"main10.rs",
+			"new.txt",
+			"clean.rs",
+			"README.md"
+		]);
+
+		// Nothing changed, by the tie-break alone
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), [
+			"src",
+			"Cargo.lock",
+			"clean.rs",
+			"main2.rs",
+			"main10.rs",
+			"new.txt",
+			"README.md"
+		]);
+	}
+
+	#[test]
+	fn test_sidecars_adjacent() {
+		let mut items: Vec<_> = [
+			"IMG_002.CR2.xmp",
+			"IMG_010.JPG",
+			"IMG_001.xmp",
+			"IMG_002.CR2",
+			"IMG_010.AAE",
+			"IMG_001.CR2.xmp",
+			"IMG_001.CR2",
+			"notes.txt",
+		]
+		.map(|n| file(&format!("/p/{n}")))
+		.into();
+
+		let mut sorter =
+			FilesSorter { by: SortBy::Natural, sidecars_adjacent: true, ..Default::default() };
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), [
+			"IMG_001.CR2",
+			"IMG_001.CR2.xmp",
+			"IMG_001.xmp",
+			"IMG_002.CR2",
+			"IMG_002.CR2.xmp",
+			"IMG_010.JPG",
+			"IMG_010.AAE",
+			"notes.txt"
+		]);
+
+		// Without it, the sidecars are apart from their primary
+		sorter.sidecars_adjacent = false;
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items)[5..], ["IMG_010.AAE", "IMG_010.JPG", "notes.txt"]);
+
+		// Only the given suffixes are sidecars, so "IMG_001.CR2.xmp" is on its own
+		sorter.by = SortBy::Alphabetical;
+		sorter.sidecars_adjacent = true;
+		sorter.sidecar_suffixes = Some(vec![".aae".to_owned()]);
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), [
+			"IMG_001.CR2",
+			"IMG_001.xmp",
+			"IMG_001.CR2.xmp",
+			"IMG_002.CR2",
+			"IMG_002.CR2.xmp",
+			"IMG_010.JPG",
+			"IMG_010.AAE",
+			"notes.txt"
+		]);
+	}
+
+	#[test]
+	fn test_sort_group_separator() {
+		let mut items: Vec<_> =
+			["file_1,000", "file_999", "file_20,500", "file_2"].map(|n| file(&format!("/d/{n}"))).into();
+
+		let mut sorter = FilesSorter { by: SortBy::Natural, ..Default::default() };
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["file_1,000", "file_2", "file_20,500", "file_999"]);
+
+		sorter.group_separator = Some(b',');
+		sorter.sort_cached(&mut items, &SortMeta::default(), &mut SortKeys::default());
+		assert_eq!(names(&items), ["file_2", "file_999", "file_1,000", "file_20,500"]);
+	}
+
+	#[test]
+	fn test_session_start() {
+		let t = |s| UNIX_EPOCH + Duration::from_secs(s);
+		let mut items = vec![
+			timed("/w/a", None, Some(1000)),
+			timed("/w/b", None, Some(1500)),
+			timed("/w/c", None, Some(999)),
+			timed("/w/d", None, None),
+			timed("/w/e", None, Some(1001)),
+			timed("/w/f", None, Some(2000)),
+		];
+
+		// The start is exclusive, "a" modified exactly then isn't promoted
+		let mut sorter =
+			FilesSorter { by: SortBy::Alphabetical, session_start: Some(t(1000)), ..Default::default() };
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["f", "b", "e", "a", "c", "d"]);
+
+		// Only the rest is reversed
+		sorter.reverse = true;
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["f", "b", "e", "d", "c", "a"]);
+
+		sorter.session_start = Some(t(2000));
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["f", "e", "d", "c", "b", "a"]);
+	}
+
+	#[test]
+	fn test_sort_child_count() {
+		let mut items = vec![
+			file("/w/b.txt"),
+			dir("/w/small"),
+			file("/w/a.txt"),
+			dir("/w/full"),
+			dir("/w/uncounted"),
+			file("/w/c10.txt"),
+			dir("/w/empty"),
+			dir("/w/medium"),
+			file("/w/c9.txt"),
+		];
+
+		let counts = [("small", 2), ("full", 1200), ("empty", 0), ("medium", 35)]
+			.into_iter()
+			.map(|(s, n)| (UrnBuf::from(s), n))
+			.collect();
+
+		let mut sorter = FilesSorter { by: SortBy::ChildCount, ..Default::default() };
+		sorter.sort(&mut items, &SortMeta { counts: Some(&counts), ..Default::default() });
+		assert_eq!(names(&items), [
+			"full",
+			"medium",
+			"small",
+			"empty",
+			"uncounted",
+			"a.txt",
+			"b.txt",
+			"c10.txt",
+			"c9.txt"
+		]);
+
+		// Files by the tie-breaks
+		sorter.tie_breaks = vec![TieBreak { by: SortBy::Natural, reverse: false }];
+		sorter.sort(&mut items, &SortMeta { counts: Some(&counts), ..Default::default() });
+		assert_eq!(names(&items)[5..], ["a.txt", "b.txt", "c9.txt", "c10.txt"]);
+	}
+
+	#[test]
+	fn test_sort_label() {
+		let mut items: Vec<_> =
+			["/w/plain", "/w/todo", "/w/done", "/w/another", "/w/urgent", "/w/later"]
+				.into_iter()
+				.map(file)
+				.collect();
+		let labels = HashMap::from_iter(
+			[
+				("plain", None),
+				("todo", Some(Label::Red)),
+				("done", Some(Label::Green)),
+				("urgent", Some(Label::Red)),
+				("later", Some(Label::Gray)),
+			]
+			.map(|(k, v)| (UrnBuf::from(k), v)),
+		);
+
+		// "another" isn't read yet, and ranks with the unlabeled ones
+		let sorter = FilesSorter { by: SortBy::Label, ..Default::default() };
+		sorter.sort(&mut items, &SortMeta { labels: Some(&labels), ..Default::default() });
+		assert_eq!(names(&items), ["todo", "urgent", "done", "later", "another", "plain"]);
+
+		// Without labels, e.g. off macOS, they all tie and go by name
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["another", "done", "later", "plain", "todo", "urgent"]);
+	}
+
+	#[test]
+	fn test_sort_collation() {
+		let mut items = vec![
+			file("/w/apple"),
+			file("/w/zebra"),
+			file("/w/Banana"),
+			file("/w/cherry"),
+			file("/w/élan"),
+			file("/w/cab"),
+			file("/w/b9"),
+			file("/w/b10"),
+		];
+
+		// An alphabet starting with "c", "b", "a", the rest after it, digits too
+		let collation = Some(Arc::new("cba".chars().collect()));
+		let mut sorter = FilesSorter { by: SortBy::Alphabetical, collation, ..Default::default() };
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["cab", "cherry", "Banana", "b10", "b9", "apple", "zebra", "élan"]);
+
+		sorter.by = SortBy::Natural;
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["cab", "cherry", "Banana", "b9", "b10", "apple", "zebra", "élan"]);
+
+		// Case matters when sensitive, and uppercase isn't in the table
+		sorter.sensitive = true;
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["cab", "cherry", "b9", "b10", "apple", "Banana", "zebra", "élan"]);
+
+		// Characters not in the table go by their code point, after those that are
+		let collation = Some(Arc::new("éz".chars().collect()));
+		let sorter = FilesSorter { by: SortBy::Alphabetical, collation, ..Default::default() };
+		sorter.sort(&mut items, &SortMeta::default());
+		assert_eq!(names(&items), ["élan", "zebra", "apple", "b10", "b9", "Banana", "cab", "cherry"]);
 	}
 }
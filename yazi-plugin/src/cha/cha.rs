@@ -48,6 +48,7 @@ impl Cha {
 				reg.add_field_method_get("uid", |_, me| Ok((!me.is_dummy()).then_some(me.uid)));
 				reg.add_field_method_get("gid", |_, me| Ok((!me.is_dummy()).then_some(me.gid)));
 				reg.add_field_method_get("nlink", |_, me| Ok((!me.is_dummy()).then_some(me.nlink)));
+				reg.add_field_method_get("blocks", |_, me| Ok((!me.is_dummy()).then_some(me.blocks)));
 			}
 
 			reg.add_field_method_get("len", |_, me| Ok(me.len));
@@ -138,6 +139,8 @@ impl Cha {
 					gid: t.raw_get("gid").unwrap_or_default(),
 					#[cfg(unix)]
 					nlink: t.raw_get("nlink").unwrap_or_default(),
+					#[cfg(unix)]
+					blocks: t.raw_get("blocks").unwrap_or_default(),
 				})
 			})?,
 		)
@@ -8,6 +8,7 @@ pub enum PreworkOp {
 	Fetch(PreworkOpFetch),
 	Load(PreworkOpLoad),
 	Size(PreworkOpSize),
+	Label(PreworkOpLabel),
 }
 
 impl PreworkOp {
@@ -16,6 +17,7 @@ impl PreworkOp {
 			Self::Fetch(op) => op.id,
 			Self::Load(op) => op.id,
 			Self::Size(op) => op.id,
+			Self::Label(op) => op.id,
 		}
 	}
 }
@@ -40,3 +42,9 @@ pub struct PreworkOpSize {
 	pub target:   Url,
 	pub throttle: Arc<Throttle<(Url, u64)>>,
 }
+
+#[derive(Debug)]
+pub struct PreworkOpLabel {
+	pub id:      usize,
+	pub targets: Vec<Url>,
+}
@@ -6,17 +6,18 @@ use tokio::sync::mpsc;
 use tracing::error;
 use yazi_config::Priority;
 use yazi_plugin::isolate;
-use yazi_shared::fs::{FilesOp, Url, calculate_size};
+use yazi_shared::fs::{FilesOp, Label, Url, calculate_size};
 
-use super::{PreworkOp, PreworkOpFetch, PreworkOpLoad, PreworkOpSize};
+use super::{PreworkOp, PreworkOpFetch, PreworkOpLabel, PreworkOpLoad, PreworkOpSize};
 use crate::{HIGH, NORMAL, TaskOp, TaskProg};
 
 pub struct Prework {
 	macro_: async_priority_channel::Sender<TaskOp, u8>,
 	prog:   mpsc::UnboundedSender<TaskProg>,
 
-	pub loaded:       Mutex<HashMap<Url, u32>>,
-	pub size_loading: RwLock<HashSet<Url>>,
+	pub loaded:        Mutex<HashMap<Url, u32>>,
+	pub size_loading:  RwLock<HashSet<Url>>,
+	pub label_loading: RwLock<HashSet<Url>>,
 }
 
 impl Prework {
@@ -24,7 +25,13 @@ impl Prework {
 		macro_: async_priority_channel::Sender<TaskOp, u8>,
 		prog: mpsc::UnboundedSender<TaskProg>,
 	) -> Self {
-		Self { macro_, prog, loaded: Default::default(), size_loading: Default::default() }
+		Self {
+			macro_,
+			prog,
+			loaded: Default::default(),
+			size_loading: Default::default(),
+			label_loading: Default::default(),
+		}
 	}
 
 	pub async fn work(&self, op: PreworkOp) -> Result<()> {
@@ -100,6 +107,29 @@ impl Prework {
 				});
 				self.prog.send(TaskProg::Adv(task.id, 1, 0))?;
 			}
+			PreworkOp::Label(task) => {
+				let Some(parent) = task.targets.first().and_then(|u| u.parent_url()) else {
+					return Ok(());
+				};
+
+				// Each is a blocking `getxattr` call on macOS
+				let id = task.id;
+				let labels = tokio::task::spawn_blocking(move || {
+					task.targets.into_iter().map(|u| (Label::read(&u), u)).collect::<Vec<_>>()
+				})
+				.await?;
+
+				{
+					let mut loading = self.label_loading.write();
+					for (_, url) in &labels {
+						loading.remove(url);
+					}
+				}
+
+				FilesOp::Label(parent, labels.into_iter().map(|(l, u)| (u.urn_owned(), l)).collect())
+					.emit();
+				self.prog.send(TaskProg::Adv(id, 1, 0))?;
+			}
 		}
 		Ok(())
 	}
@@ -135,6 +165,14 @@ impl Prework {
 		self.work(PreworkOp::Size(task)).await?;
 		self.succ(id)
 	}
+
+	pub async fn label(&self, task: PreworkOpLabel) -> Result<()> {
+		let id = task.id;
+
+		self.prog.send(TaskProg::New(id, 0))?;
+		self.work(PreworkOp::Label(task)).await?;
+		self.succ(id)
+	}
 }
 
 impl Prework {
@@ -10,7 +10,7 @@ use yazi_proxy::ManagerProxy;
 use yazi_shared::{Throttle, event::Data, fs::{Url, remove_dir_clean, unique_name}};
 
 use super::{Ongoing, TaskProg, TaskStage};
-use crate::{HIGH, LOW, NORMAL, TaskKind, TaskOp, file::{File, FileOpDelete, FileOpHardlink, FileOpLink, FileOpPaste, FileOpTrash}, plugin::{Plugin, PluginOpEntry}, prework::{Prework, PreworkOpFetch, PreworkOpLoad, PreworkOpSize}, process::{Process, ProcessOpBg, ProcessOpBlock, ProcessOpOrphan}};
+use crate::{HIGH, LOW, NORMAL, TaskKind, TaskOp, file::{File, FileOpDelete, FileOpHardlink, FileOpLink, FileOpPaste, FileOpTrash}, plugin::{Plugin, PluginOpEntry}, prework::{Prework, PreworkOpFetch, PreworkOpLabel, PreworkOpLoad, PreworkOpSize}, process::{Process, ProcessOpBg, ProcessOpBlock, ProcessOpOrphan}};
 
 pub struct Scheduler {
 	pub file:    Arc<File>,
@@ -266,6 +266,15 @@ impl Scheduler {
 		}
 	}
 
+	pub fn prework_label(&self, targets: Vec<&Url>) {
+		let Some(parent) = targets.first().and_then(|u| u.parent_url()) else { return };
+		let id = self.ongoing.lock().add(TaskKind::Preload, format!("Read the labels in {parent}"));
+
+		let targets = targets.into_iter().cloned().collect();
+		let prework = self.prework.clone();
+		self.send_micro(id, NORMAL, async move { prework.label(PreworkOpLabel { id, targets }).await });
+	}
+
 	pub fn process_open(
 		&self,
 		opener: Cow<'static, Opener>,
@@ -18,21 +18,24 @@ bitflags! {
 
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub struct Cha {
-	pub kind:  ChaKind,
-	pub len:   u64,
-	pub atime: Option<SystemTime>,
-	pub btime: Option<SystemTime>,
+	pub kind:   ChaKind,
+	pub len:    u64,
+	pub atime:  Option<SystemTime>,
+	pub btime:  Option<SystemTime>,
 	#[cfg(unix)]
-	pub ctime: Option<SystemTime>,
-	pub mtime: Option<SystemTime>,
+	pub ctime:  Option<SystemTime>,
+	pub mtime:  Option<SystemTime>,
 	#[cfg(unix)]
-	pub perm:  libc::mode_t,
+	pub perm:   libc::mode_t,
 	#[cfg(unix)]
-	pub uid:   libc::uid_t,
+	pub uid:    libc::uid_t,
 	#[cfg(unix)]
-	pub gid:   libc::gid_t,
+	pub gid:    libc::gid_t,
 	#[cfg(unix)]
-	pub nlink: libc::nlink_t,
+	pub nlink:  libc::nlink_t,
+	// Allocated on disk, in 512-byte units, less than `len` for sparse or compressed files
+	#[cfg(unix)]
+	pub blocks: u64,
 }
 
 impl From<Metadata> for Cha {
@@ -55,25 +58,30 @@ impl From<Metadata> for Cha {
 			mtime:              m.modified().ok(),
 
 			#[cfg(unix)]
-			perm:               {
+			perm:                {
 				use std::os::unix::prelude::PermissionsExt;
 				m.permissions().mode() as _
 			},
 			#[cfg(unix)]
-			uid:                {
+			uid:                 {
 				use std::os::unix::fs::MetadataExt;
 				m.uid() as _
 			},
 			#[cfg(unix)]
-			gid:                {
+			gid:                 {
 				use std::os::unix::fs::MetadataExt;
 				m.gid() as _
 			},
 			#[cfg(unix)]
-			nlink:              {
+			nlink:               {
 				use std::os::unix::fs::MetadataExt;
 				m.nlink() as _
 			},
+			#[cfg(unix)]
+			blocks:              {
+				use std::os::unix::fs::MetadataExt;
+				m.blocks()
+			},
 		}
 	}
 }
@@ -144,6 +152,10 @@ impl Cha {
 }
 
 impl Cha {
+	/// The size allocated on disk, the same as `len` where it isn't known.
+	#[inline]
+	pub const fn allocated(&self) -> u64 { unix_either!(self.blocks * 512, self.len) }
+
 	#[inline]
 	pub const fn is_dir(&self) -> bool { self.kind.contains(ChaKind::DIR) }
 
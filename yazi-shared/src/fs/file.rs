@@ -101,4 +101,9 @@ impl File {
 
 	#[inline]
 	pub fn stem(&self) -> Option<&OsStr> { self.url.file_stem() }
+
+	// --- Cha
+	/// A symlink whose target doesn't exist.
+	#[inline]
+	pub fn is_broken_link(&self) -> bool { self.cha.is_orphan() }
 }
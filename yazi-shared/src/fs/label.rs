@@ -0,0 +1,84 @@
+use std::path::Path;
+
+/// A color label, as macOS Finder keeps it in the `com.apple.FinderInfo`
+/// extended attribute, in the order Finder lists them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Label {
+	Red,
+	Orange,
+	Yellow,
+	Green,
+	Blue,
+	Purple,
+	Gray,
+}
+
+impl Label {
+	/// Reads the label of the file at `path`, without following symlinks.
+	/// `None` if it has none, or extended attributes can't tell, as off macOS.
+	pub fn read(path: &Path) -> Option<Self> {
+		#[cfg(target_os = "macos")]
+		{
+			use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+			let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+			let mut info = [0u8; 32];
+			let n = unsafe {
+				libc::getxattr(
+					path.as_ptr(),
+					c"com.apple.FinderInfo".as_ptr(),
+					info.as_mut_ptr().cast(),
+					info.len(),
+					0,
+					libc::XATTR_NOFOLLOW,
+				)
+			};
+			Self::from_finder_info(info.get(..usize::try_from(n).ok()?)?)
+		}
+		#[cfg(not(target_os = "macos"))]
+		{
+			_ = path;
+			None
+		}
+	}
+
+	// The label in the 32 bytes of `FinderInfo`, bits 1-3 of its flags at 8..10
+	#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+	fn from_finder_info(info: &[u8]) -> Option<Self> {
+		Some(match (info.get(9)? >> 1) & 0b111 {
+			1 => Self::Gray,
+			2 => Self::Green,
+			3 => Self::Purple,
+			4 => Self::Blue,
+			5 => Self::Yellow,
+			6 => Self::Red,
+			7 => Self::Orange,
+			_ => return None,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_from_finder_info() {
+		let info = |flags: u8| {
+			let mut info = [0u8; 32];
+			info[9] = flags;
+			info
+		};
+
+		assert_eq!(Label::from_finder_info(&info(0b1100)), Some(Label::Red));
+		assert_eq!(Label::from_finder_info(&info(0b1110)), Some(Label::Orange));
+		assert_eq!(Label::from_finder_info(&info(0b0010)), Some(Label::Gray));
+		// Other Finder flags around it don't matter
+		assert_eq!(Label::from_finder_info(&info(0b0100_0101)), Some(Label::Green));
+
+		assert_eq!(Label::from_finder_info(&info(0)), None);
+		assert_eq!(Label::from_finder_info(&info(0b0001)), None);
+		assert_eq!(Label::from_finder_info(&[0; 9]), None);
+		assert_eq!(Label::from_finder_info(&[]), None);
+	}
+}
@@ -1 +1 @@
-yazi_macro::mod_flat!(cha file fns loc op path url urn);
+yazi_macro::mod_flat!(cha file fns label loc op path url urn);
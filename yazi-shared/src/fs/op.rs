@@ -1,6 +1,6 @@
 use std::{collections::{HashMap, HashSet}, sync::atomic::{AtomicU64, Ordering}};
 
-use super::{Cha, File, UrnBuf};
+use super::{Cha, File, Label, UrnBuf};
 use crate::{Layer, event::Cmd, fs::Url};
 
 pub static FILES_TICKET: AtomicU64 = AtomicU64::new(0);
@@ -11,6 +11,7 @@ pub enum FilesOp {
 	Part(Url, Vec<File>, u64),
 	Done(Url, Cha, u64),
 	Size(Url, HashMap<UrnBuf, u64>),
+	Label(Url, HashMap<UrnBuf, Option<Label>>),
 	IOErr(Url, std::io::ErrorKind),
 
 	Creating(Url, Vec<File>),
@@ -27,6 +28,7 @@ impl FilesOp {
 			Self::Part(u, ..) => u,
 			Self::Done(u, ..) => u,
 			Self::Size(u, _) => u,
+			Self::Label(u, _) => u,
 			Self::IOErr(u, _) => u,
 
 			Self::Creating(u, _) => u,
@@ -106,6 +108,7 @@ impl FilesOp {
 			Self::Part(_, files, ticket) => Self::Part(n, files!(files), *ticket),
 			Self::Done(_, cha, ticket) => Self::Done(n, *cha, *ticket),
 			Self::Size(_, map) => Self::Size(n, map.iter().map(|(u, &s)| (u.clone(), s)).collect()),
+			Self::Label(_, map) => Self::Label(n, map.clone()),
 			Self::IOErr(_, err) => Self::IOErr(n, *err),
 
 			Self::Creating(_, files) => Self::Creating(n, files!(files)),
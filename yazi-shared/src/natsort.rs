@@ -6,6 +6,8 @@
 
 use std::cmp::Ordering;
 
+use crate::translit::Transliterator;
+
 macro_rules! return_unless_equal {
 	($ord:expr) => {
 		match $ord {
@@ -64,7 +66,162 @@ fn compare_right(left: &[u8], right: &[u8], li: &mut usize, ri: &mut usize) -> O
 	}
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NatsortOpts {
+	pub insensitive:     bool,
+	// Compare digit runs as decimal numbers using the locale's separators,
+	// instead of treating the separators as plain text.
+	pub locale:          Option<NumericLocale>,
+	// Treat a `-` right before a number as its sign, so "delta-10" sorts before "delta-5".
+	pub signed_numbers:  bool,
+	// Sort names starting with a digit after all others, so "Apple" comes before "2nd".
+	pub digits_last:     bool,
+	// Read digits grouped in threes by this separator, like "1,000", as one number,
+	// without taking anything as a decimal point as `locale` does.
+	pub group_separator: Option<u8>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NumericLocale {
+	pub decimal: u8,
+	pub group:   Option<u8>,
+}
+
+impl NumericLocale {
+	pub const COMMA: Self = Self { decimal: b',', group: Some(b'.') };
+	pub const DOT: Self = Self { decimal: b'.', group: Some(b',') };
+
+	/// Picks the separators from a POSIX locale name like `de_DE.UTF-8`.
+	pub fn from_locale(name: &str) -> Option<Self> {
+		let lang = name.split(['_', '-', '.', '@']).next()?;
+		Some(match lang {
+			"C" | "POSIX" | "en" | "ja" | "ko" | "zh" | "th" | "he" => Self::DOT,
+			"de" | "es" | "it" | "pt" | "nl" | "tr" | "id" | "da" | "el" | "ro" | "vi" => Self::COMMA,
+			// These group digits with spaces, which are already skipped
+			"fr" | "ru" | "pl" | "cs" | "sk" | "sv" | "fi" | "nb" | "uk" | "hu" => {
+				Self { decimal: b',', group: None }
+			}
+			_ => return None,
+		})
+	}
+
+	// Reads a number starting at `i`, returning its integer digits without group
+	// separators and leading zeros, its fraction digits, and the index after it.
+	fn read<'a>(&self, s: &'a [u8], i: usize) -> (Vec<u8>, &'a [u8], usize) {
+		let (int, i) = read_grouped(s, i, self.group);
+
+		if s.get(i) == Some(&self.decimal) {
+			let n = digits(s, i + 1);
+			if n > 0 {
+				let frac = &s[i + 1..i + 1 + n];
+				return (
+					int,
+					&frac[..frac.iter().rposition(|&b| b != b'0').map_or(0, |p| p + 1)],
+					i + 1 + n,
+				);
+			}
+		}
+		(int, &[], i)
+	}
+}
+
+#[inline]
+fn digits(s: &[u8], i: usize) -> usize {
+	s[i.min(s.len())..].iter().take_while(|b| b.is_ascii_digit()).count()
+}
+
+// Whether the digits starting at `i` are followed by `group` and three more
+#[inline]
+fn is_grouped(s: &[u8], i: usize, group: u8) -> bool {
+	let n = digits(s, i);
+	s.get(i + n) == Some(&group) && digits(s, i + n + 1) == 3
+}
+
+// Reads the digits starting at `i`, along with the ones following `group` in
+// runs of three, returning them without the separators and leading zeros, and
+// the index after them
+fn read_grouped(s: &[u8], mut i: usize, group: Option<u8>) -> (Vec<u8>, usize) {
+	let mut int = Vec::with_capacity(8);
+	loop {
+		let n = digits(s, i);
+		let run = &s[i..i + n];
+		if int.is_empty() {
+			int.extend(run.iter().skip_while(|&&b| b == b'0'));
+		} else {
+			int.extend_from_slice(run);
+		}
+		i += n;
+
+		if group.is_some_and(|g| s.get(i) == Some(&g)) && digits(s, i + 1) == 3 {
+			i += 1;
+		} else {
+			break;
+		}
+	}
+	(int, i)
+}
+
+// Compares the numbers starting at `li` and `ri`, moving past them if equal
+fn compare_numbers(
+	left: &[u8],
+	right: &[u8],
+	li: &mut usize,
+	ri: &mut usize,
+	opts: NatsortOpts,
+) -> Ordering {
+	if let Some(locale) = opts.locale {
+		let (l_int, l_frac, l_end) = locale.read(left, *li);
+		let (r_int, r_frac, r_end) = locale.read(right, *ri);
+		return_unless_equal!(l_int.len().cmp(&r_int.len()));
+		return_unless_equal!(l_int.cmp(&r_int));
+		return_unless_equal!(l_frac.cmp(r_frac));
+
+		(*li, *ri) = (l_end, r_end);
+		return Ordering::Equal;
+	}
+
+	// Only numbers actually grouped, so the others keep their leading zeros
+
+	let grouped = |g: &u8| is_grouped(left, *li, *g) || is_grouped(right, *ri, *g);
+	if let Some(group) = opts.group_separator.filter(grouped) {
+		let (l_int, l_end) = read_grouped(left, *li, Some(group));
+		let (r_int, r_end) = read_grouped(right, *ri, Some(group));
+		return_unless_equal!(l_int.len().cmp(&r_int.len()));
+		return_unless_equal!(l_int.cmp(&r_int));
+
+		(*li, *ri) = (l_end, r_end);
+		return Ordering::Equal;
+	}
+
+	if left[*li] == b'0' || right[*ri] == b'0' {
+		compare_left(left, right, li, ri)
+	} else {
+		compare_right(left, right, li, ri)
+	}
+}
+
+// A `-` followed by digits is a minus sign, unless it follows digits itself,
+// like in a date `2024-01-05` or a range `1-10`
+#[inline]
+fn is_negative(s: &[u8], i: usize) -> bool {
+	s[i] == b'-'
+		&& s.get(i + 1).is_some_and(u8::is_ascii_digit)
+		&& (i == 0 || !s[i - 1].is_ascii_digit())
+}
+
+#[inline]
 pub fn natsort(left: &[u8], right: &[u8], insensitive: bool) -> Ordering {
+	natsort_with(left, right, NatsortOpts { insensitive, ..Default::default() })
+}
+
+pub fn natsort_with(left: &[u8], right: &[u8], opts: NatsortOpts) -> Ordering {
+	if opts.digits_last {
+		// All of them, not only the ones against a letter, to keep the order transitive
+		let leading =
+			|s: &[u8]| s.iter().find(|b| !b.is_ascii_whitespace()).is_some_and(u8::is_ascii_digit);
+		return_unless_equal!(leading(left).cmp(&leading(right)));
+	}
+
 	let mut li = 0;
 	let mut ri = 0;
 
@@ -95,19 +252,31 @@ pub fn natsort(left: &[u8], right: &[u8], insensitive: bool) -> Ordering {
 
 		match (l, r) {
 			(Some(&ll), Some(&rr)) => {
-				if ll.is_ascii_digit() && rr.is_ascii_digit() {
-					if ll == b'0' || rr == b'0' {
-						return_unless_equal!(compare_left(left, right, &mut li, &mut ri));
-					} else {
-						return_unless_equal!(compare_right(left, right, &mut li, &mut ri));
+				if opts.signed_numbers {
+					match (is_negative(left, li), is_negative(right, ri)) {
+						(true, true) => {
+							(li, ri) = (li + 1, ri + 1);
+							return_unless_equal!(compare_numbers(left, right, &mut li, &mut ri, opts).reverse());
+
+							l = left.get(li);
+							r = right.get(ri);
+							continue;
+						}
+						(true, false) if rr.is_ascii_digit() => return Ordering::Less,
+						(false, true) if ll.is_ascii_digit() => return Ordering::Greater,
+						_ => {}
 					}
+				}
+
+				if ll.is_ascii_digit() && rr.is_ascii_digit() {
+					return_unless_equal!(compare_numbers(left, right, &mut li, &mut ri, opts));
 
 					l = left.get(li);
 					r = right.get(ri);
 					continue;
 				}
 
-				if insensitive {
+				if opts.insensitive {
 					return_unless_equal!(ll.to_ascii_lowercase().cmp(&rr.to_ascii_lowercase()));
 				} else {
 					return_unless_equal!(ll.cmp(&rr));
@@ -123,6 +292,45 @@ pub fn natsort(left: &[u8], right: &[u8], insensitive: bool) -> Ordering {
 	}
 }
 
+/// Builds a byte key whose `Ord` matches [`natsort`] on the same names, so
+/// external sorters can reproduce the natural order without calling back.
+///
+/// Digit runs are encoded as a marker byte within `b'0'..=b'9'`, so that they
+/// still compare against non-digits as a digit would:
+/// - runs with a leading zero: `b'0'`, the digits, then a `0x00` terminator;
+/// - other runs: `b'1'`, the run length as `u32`, then the digits.
+pub fn natural_key(name: &[u8], translit: bool, insensitive: bool) -> Vec<u8> {
+	let name = if translit { name.transliterate() } else { String::from_utf8_lossy(name) };
+	let name = name.as_bytes();
+
+	let mut key = Vec::with_capacity(name.len() + 8);
+	let mut i = 0;
+	while let Some(&b) = name.get(i) {
+		if b.is_ascii_whitespace() {
+			i += 1;
+			continue;
+		}
+		if !b.is_ascii_digit() {
+			key.push(if insensitive { b.to_ascii_lowercase() } else { b });
+			i += 1;
+			continue;
+		}
+
+		let n = name[i..].iter().take_while(|b| b.is_ascii_digit()).count();
+		if b == b'0' {
+			key.push(b'0');
+			key.extend_from_slice(&name[i..i + n]);
+			key.push(0);
+		} else {
+			key.push(b'1');
+			key.extend_from_slice(&(n as u32).to_be_bytes());
+			key.extend_from_slice(&name[i..i + n]);
+		}
+		i += n;
+	}
+	key
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -176,4 +384,210 @@ mod tests {
 		cmp(&fractions);
 		cmp(&words);
 	}
+
+	#[test]
+	fn test_natural_key() {
+		let names = [
+			"1-02",
+			"1-2",
+			"1-20",
+			"10-20",
+			"fred",
+			"Jane",
+			"pic01",
+			"pic02",
+			"pic02a",
+			"pic02000",
+			"pic05",
+			"pic2",
+			"pic3",
+			"pic 4 else",
+			"pic 5",
+			"pic 5 ",
+			"pic   7",
+			"pic100",
+			"pic100a",
+			"x2-g8",
+			"x2-y08",
+			"x2-y7",
+			"PIC2",
+			"a:b",
+			"a5",
+			"a-5",
+			"1.002.01",
+			"1.009.10",
+			"1.010.12",
+			"v1 2",
+			"v12",
+		];
+
+		for insensitive in [true, false] {
+			for a in names {
+				for b in names {
+					assert_eq!(
+						natural_key(a.as_bytes(), false, insensitive).cmp(&natural_key(
+							b.as_bytes(),
+							false,
+							insensitive
+						)),
+						natsort(a.as_bytes(), b.as_bytes(), insensitive),
+						"{a:?} vs {b:?}, insensitive: {insensitive}"
+					);
+				}
+			}
+		}
+
+		assert_eq!(natural_key("café2".as_bytes(), true, true), natural_key(b"cafe2", false, true));
+	}
+
+	#[test]
+	fn test_natsort_locale() {
+		fn sort<'a>(names: &[&'a str], locale: Option<NumericLocale>) -> Vec<&'a str> {
+			let mut v = names.to_vec();
+			v.sort_by(|a, b| {
+				natsort_with(a.as_bytes(), b.as_bytes(), NatsortOpts { locale, ..Default::default() })
+			});
+			v
+		}
+
+		let names = ["a1,5", "a10,5", "a1,25", "a2", "a1,05", "a1.000,5", "a1,5 b", "a1,x"];
+		assert_eq!(sort(&names, NumericLocale::from_locale("de_DE.UTF-8")), [
+			"a1,x", "a1,05", "a1,25", "a1,5", "a1,5 b", "a2", "a10,5", "a1.000,5"
+		]);
+		assert_eq!(sort(&names, None), [
+			"a1,05", "a1,5", "a1,5 b", "a1,25", "a1,x", "a1.000,5", "a2", "a10,5"
+		]);
+
+		assert_eq!(NumericLocale::from_locale("en_US.UTF-8"), Some(NumericLocale::DOT));
+		assert_eq!(
+			NumericLocale::from_locale("fr_FR"),
+			Some(NumericLocale { decimal: b',', group: None })
+		);
+		assert_eq!(NumericLocale::from_locale("xx"), None);
+	}
+
+	#[test]
+	fn test_natsort_long_digits() {
+		let long = |prefix: &str, n: usize, last: char| format!("{prefix}{}{last}", "9".repeat(n));
+
+		let names = [
+			"a1".to_owned(),
+			"a99".to_owned(),
+			long("a", 18, '9'),
+			long("a1", 19, '0'),
+			long("a1", 19, '1'),
+			long("a", 1_000_000, '8'),
+			long("a", 1_000_000, '9'),
+			"b".to_owned(),
+		];
+		cmp(&names.iter().map(|s| s.as_str()).collect::<Vec<_>>());
+	}
+
+	#[test]
+	fn test_natsort_transitive() {
+		let zeros = |n: usize, last: &str| format!("a{}{last}", "0".repeat(n));
+		let names = [
+			"a010".to_owned(),
+			"a09".to_owned(),
+			"a9".to_owned(),
+			"a10".to_owned(),
+			zeros(1, "10"),
+			zeros(21, "9"),
+			zeros(25, "10"),
+			zeros(30, "1"),
+			format!("a{}", "9".repeat(25)),
+			format!("a1{}", "0".repeat(25)),
+		];
+
+		let ord = |a: &String, b: &String| natsort(a.as_bytes(), b.as_bytes(), false);
+		for a in &names {
+			for b in &names {
+				for c in &names {
+					if ord(a, b).is_le() && ord(b, c).is_le() {
+						assert!(ord(a, c).is_le(), "{a} <= {b} <= {c}");
+					}
+				}
+			}
+		}
+
+		let mut sorted = names.to_vec();
+		sorted.sort_unstable_by(ord);
+		assert!(sorted.windows(2).all(|w| ord(&w[0], &w[1]).is_le()));
+	}
+
+	#[test]
+	fn test_natsort_signed() {
+		fn sort<'a>(names: &[&'a str], signed_numbers: bool) -> Vec<&'a str> {
+			let opts = NatsortOpts { signed_numbers, ..Default::default() };
+			let mut v = names.to_vec();
+			v.sort_by(|a, b| natsort_with(a.as_bytes(), b.as_bytes(), opts));
+			v
+		}
+
+		let names = ["delta-5", "delta2", "delta-10", "delta0", "delta-", "delta-x", "delta-05"];
+		assert_eq!(sort(&names, false), [
+			"delta-", "delta-05", "delta-5", "delta-10", "delta-x", "delta0", "delta2"
+		]);
+		assert_eq!(sort(&names, true), [
+			"delta-", "delta-10", "delta-5", "delta-05", "delta-x", "delta0", "delta2"
+		]);
+
+		// Dates and ranges are unaffected
+		let names = ["2024-10-01", "2024-9-30", "1-10", "1-2"];
+		assert_eq!(sort(&names, true), sort(&names, false));
+	}
+
+	#[test]
+	fn test_natsort_digits_last() {
+		fn sort<'a>(names: &[&'a str], digits_last: bool) -> Vec<&'a str> {
+			let opts = NatsortOpts { insensitive: true, digits_last, ..Default::default() };
+			let mut v = names.to_vec();
+			v.sort_by(|a, b| natsort_with(a.as_bytes(), b.as_bytes(), opts));
+			v
+		}
+
+		let names = ["banana", "2nd", "Apple", "10th", "_index", "apple2", "apple10", " 3rd"];
+		assert_eq!(sort(&names, false), [
+			"2nd", " 3rd", "10th", "_index", "Apple", "apple2", "apple10", "banana"
+		]);
+		assert_eq!(sort(&names, true), [
+			"_index", "Apple", "apple2", "apple10", "banana", "2nd", " 3rd", "10th"
+		]);
+	}
+
+	#[test]
+	fn test_natsort_group_separator() {
+		fn sort<'a>(names: &[&'a str], group_separator: Option<u8>) -> Vec<&'a str> {
+			let opts = NatsortOpts { group_separator, ..Default::default() };
+			let mut v = names.to_vec();
+			v.sort_by(|a, b| natsort_with(a.as_bytes(), b.as_bytes(), opts));
+			v
+		}
+
+		let names = ["file_1,000", "file_999", "file_1,000,000", "file_20,500", "file_2", "file_1,5"];
+		assert_eq!(sort(&names, None), [
+			"file_1,000",
+			"file_1,000,000",
+			"file_1,5",
+			"file_2",
+			"file_20,500",
+			"file_999"
+		]);
+		assert_eq!(sort(&names, Some(b',')), [
+			"file_1,5",
+			"file_2",
+			"file_999",
+			"file_1,000",
+			"file_20,500",
+			"file_1,000,000"
+		]);
+
+		// Any separator, and the digits after a decimal point aren't a fraction
+		let names = ["v1'000.5", "v1'000.10", "v999.9", "v12"];
+		assert_eq!(sort(&names, Some(b'\'')), ["v12", "v999.9", "v1'000.5", "v1'000.10"]);
+
+		// Ungrouped numbers are compared as before
+		let names = ["pic01", "pic2", "pic1,000", "pic02"];
+		assert_eq!(sort(&names, Some(b',')), ["pic01", "pic02", "pic2", "pic1,000"]);
+	}
 }
@@ -8,6 +8,9 @@ impl LcgRng {
 	const A: u64 = 6364136223846793005;
 	const C: u64 = 1;
 	const M: u64 = u64::MAX;
+
+	#[inline]
+	pub fn with_seed(seed: u64) -> Self { Self { seed } }
 }
 
 impl Iterator for LcgRng {